@@ -0,0 +1,3 @@
+ALTER TABLE servers ADD COLUMN webhook_url TEXT;
+ALTER TABLE servers ADD COLUMN webhook_name TEXT;
+ALTER TABLE servers ADD COLUMN webhook_avatar TEXT;
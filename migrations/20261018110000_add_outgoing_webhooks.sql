@@ -0,0 +1,5 @@
+CREATE TABLE outgoing_webhooks (
+    server_id BIGINT NOT NULL,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL
+);
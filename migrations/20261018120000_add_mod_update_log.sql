@@ -0,0 +1,10 @@
+CREATE TABLE mod_update_log (
+    name TEXT NOT NULL,
+    title TEXT NOT NULL,
+    author TEXT NOT NULL,
+    version TEXT NOT NULL,
+    state TEXT NOT NULL,
+    changelog TEXT NOT NULL,
+    released_at BIGINT NOT NULL
+);
+CREATE INDEX mod_update_log_released_at ON mod_update_log (released_at);
@@ -0,0 +1,11 @@
+CREATE TABLE notification_styles (
+    server_id BIGINT PRIMARY KEY NOT NULL,
+    title_format TEXT,
+    color_updated INT,
+    color_new INT,
+    show_author BOOLEAN,
+    show_version BOOLEAN,
+    show_thumbnail BOOLEAN,
+    changelog_lines INT,
+    ping_role BIGINT
+);
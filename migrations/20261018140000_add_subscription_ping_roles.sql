@@ -0,0 +1,2 @@
+ALTER TABLE subscribed_mods ADD COLUMN ping_role BIGINT;
+ALTER TABLE subscribed_authors ADD COLUMN ping_role BIGINT;
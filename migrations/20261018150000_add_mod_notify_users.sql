@@ -0,0 +1,6 @@
+CREATE TABLE mod_notify_users (
+    user_id BIGINT NOT NULL,
+    mod_name TEXT NOT NULL,
+    PRIMARY KEY (user_id, mod_name)
+);
+CREATE INDEX mod_notify_users_mod_name ON mod_notify_users (mod_name);
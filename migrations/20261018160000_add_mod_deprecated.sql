@@ -0,0 +1 @@
+ALTER TABLE mods ADD COLUMN deprecated BOOLEAN NOT NULL DEFAULT FALSE;
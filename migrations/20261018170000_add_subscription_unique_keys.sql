@@ -0,0 +1,9 @@
+DELETE FROM subscribed_mods WHERE rowid NOT IN (
+    SELECT MIN(rowid) FROM subscribed_mods GROUP BY server_id, mod_name
+);
+CREATE UNIQUE INDEX subscribed_mods_server_mod ON subscribed_mods (server_id, mod_name);
+
+DELETE FROM subscribed_authors WHERE server_id IS NULL OR author_name IS NULL OR rowid NOT IN (
+    SELECT MIN(rowid) FROM subscribed_authors GROUP BY server_id, author_name
+);
+CREATE UNIQUE INDEX subscribed_authors_server_author ON subscribed_authors (server_id, author_name);
@@ -0,0 +1,24 @@
+-- Every saved version of an FAQ entry, numbered per title
+CREATE TABLE faq_revisions (
+    server_id BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    revision INT NOT NULL,
+    contents TEXT,
+    image TEXT,
+    edit_time BIGINT NOT NULL,
+    author BIGINT NOT NULL,
+    PRIMARY KEY (server_id, title, revision)
+);
+
+-- Older versions could store a title twice, keep only the most recently edited row of each title
+DELETE FROM faq WHERE rowid NOT IN (
+    SELECT rowid FROM (
+        SELECT rowid, row_number() OVER (PARTITION BY server_id, title ORDER BY edit_time DESC, rowid DESC) AS position
+        FROM faq
+    ) WHERE position = 1
+);
+CREATE UNIQUE INDEX faq_server_title ON faq (server_id, title);
+
+-- Existing entries become their first revision, links have no content of their own
+INSERT INTO faq_revisions (server_id, title, revision, contents, image, edit_time, author)
+SELECT server_id, title, 1, contents, image, edit_time, author FROM faq WHERE link IS NULL;
@@ -0,0 +1,23 @@
+-- Alternative names for FAQ entries, replacing link rows in the faq table
+CREATE TABLE faq_aliases (
+    server_id BIGINT NOT NULL,
+    alias TEXT NOT NULL,
+    title TEXT NOT NULL,
+    PRIMARY KEY (server_id, alias)
+);
+CREATE INDEX faq_aliases_title ON faq_aliases (server_id, title);
+
+-- Convert link rows, following chains of links to the entry they end at
+WITH RECURSIVE resolved (server_id, alias, target, depth) AS (
+    SELECT server_id, title, link, 0 FROM faq WHERE link IS NOT NULL
+    UNION ALL
+    SELECT r.server_id, r.alias, f.link, r.depth + 1
+    FROM resolved r JOIN faq f ON f.server_id = r.server_id AND f.title = r.target
+    WHERE f.link IS NOT NULL AND r.depth < 10
+)
+INSERT OR IGNORE INTO faq_aliases (server_id, alias, title)
+SELECT r.server_id, r.alias, r.target
+FROM resolved r JOIN faq f ON f.server_id = r.server_id AND f.title = r.target AND f.link IS NULL;
+
+DELETE FROM faq WHERE link IS NOT NULL;
+ALTER TABLE faq DROP COLUMN link;
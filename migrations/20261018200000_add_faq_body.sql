@@ -0,0 +1,3 @@
+-- Sections, extra images, footer and link buttons of an FAQ entry as JSON
+ALTER TABLE faq ADD COLUMN body TEXT;
+ALTER TABLE faq_revisions ADD COLUMN body TEXT;
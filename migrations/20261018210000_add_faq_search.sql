@@ -0,0 +1,70 @@
+-- Full-text index of FAQ titles, aliases, contents and sections for /faq_search
+CREATE VIRTUAL TABLE faq_search USING fts5 (
+    title,
+    aliases,
+    contents,
+    sections,
+    server_id UNINDEXED,
+    tokenize = 'porter unicode61'
+);
+
+-- Kept in sync by the triggers below, rows are matched by server and title
+CREATE TRIGGER faq_search_insert AFTER INSERT ON faq BEGIN
+    INSERT INTO faq_search (title, aliases, contents, sections, server_id)
+    VALUES (
+        new.title,
+        (SELECT group_concat(alias, ' ') FROM faq_aliases WHERE server_id = new.server_id AND title = new.title),
+        new.contents,
+        (SELECT group_concat(json_extract(value, '$.name') || ' ' || json_extract(value, '$.value'), ' ')
+            FROM json_each(new.body, '$.fields') WHERE json_valid(new.body)),
+        new.server_id
+    );
+END;
+
+CREATE TRIGGER faq_search_delete AFTER DELETE ON faq BEGIN
+    DELETE FROM faq_search WHERE server_id = old.server_id AND title = old.title;
+END;
+
+CREATE TRIGGER faq_search_update AFTER UPDATE ON faq BEGIN
+    DELETE FROM faq_search WHERE server_id = old.server_id AND title = old.title;
+    INSERT INTO faq_search (title, aliases, contents, sections, server_id)
+    VALUES (
+        new.title,
+        (SELECT group_concat(alias, ' ') FROM faq_aliases WHERE server_id = new.server_id AND title = new.title),
+        new.contents,
+        (SELECT group_concat(json_extract(value, '$.name') || ' ' || json_extract(value, '$.value'), ' ')
+            FROM json_each(new.body, '$.fields') WHERE json_valid(new.body)),
+        new.server_id
+    );
+END;
+
+CREATE TRIGGER faq_search_alias_insert AFTER INSERT ON faq_aliases BEGIN
+    UPDATE faq_search
+    SET aliases = (SELECT group_concat(alias, ' ') FROM faq_aliases WHERE server_id = new.server_id AND title = new.title)
+    WHERE server_id = new.server_id AND title = new.title;
+END;
+
+CREATE TRIGGER faq_search_alias_delete AFTER DELETE ON faq_aliases BEGIN
+    UPDATE faq_search
+    SET aliases = (SELECT group_concat(alias, ' ') FROM faq_aliases WHERE server_id = old.server_id AND title = old.title)
+    WHERE server_id = old.server_id AND title = old.title;
+END;
+
+CREATE TRIGGER faq_search_alias_update AFTER UPDATE ON faq_aliases BEGIN
+    UPDATE faq_search
+    SET aliases = (SELECT group_concat(alias, ' ') FROM faq_aliases WHERE server_id = old.server_id AND title = old.title)
+    WHERE server_id = old.server_id AND title = old.title;
+    UPDATE faq_search
+    SET aliases = (SELECT group_concat(alias, ' ') FROM faq_aliases WHERE server_id = new.server_id AND title = new.title)
+    WHERE server_id = new.server_id AND title = new.title;
+END;
+
+INSERT INTO faq_search (title, aliases, contents, sections, server_id)
+SELECT
+    f.title,
+    (SELECT group_concat(alias, ' ') FROM faq_aliases a WHERE a.server_id = f.server_id AND a.title = f.title),
+    f.contents,
+    (SELECT group_concat(json_extract(value, '$.name') || ' ' || json_extract(value, '$.value'), ' ')
+        FROM json_each(f.body, '$.fields') WHERE json_valid(f.body)),
+    f.server_id
+FROM faq f;
@@ -0,0 +1,2 @@
+-- Category an FAQ entry is listed under in the FAQ index
+ALTER TABLE faq ADD COLUMN category TEXT;
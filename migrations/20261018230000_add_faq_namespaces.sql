@@ -0,0 +1,19 @@
+-- Shared namespaces a server publishes FAQ entries to
+CREATE TABLE faq_namespaces (
+    name TEXT PRIMARY KEY NOT NULL,
+    server_id BIGINT NOT NULL
+);
+
+-- Entries of the owning server published to a namespace, shown as they are on that server
+CREATE TABLE faq_namespace_entries (
+    namespace TEXT NOT NULL,
+    title TEXT NOT NULL,
+    PRIMARY KEY (namespace, title)
+);
+
+-- Namespaces a server looks up FAQ entries in when it has none of its own
+CREATE TABLE faq_namespace_subscriptions (
+    server_id BIGINT NOT NULL,
+    namespace TEXT NOT NULL,
+    PRIMARY KEY (server_id, namespace)
+);
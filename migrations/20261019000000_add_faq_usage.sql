@@ -0,0 +1,14 @@
+-- Every /faq request, to find out which entries are used and which are missing
+CREATE TABLE faq_usage (
+    server_id BIGINT NOT NULL,
+    query TEXT NOT NULL,
+    -- Entry shown, NULL if nothing matched
+    title TEXT,
+    -- Namespace the entry was shared from, NULL for entries of the server itself
+    namespace TEXT,
+    -- exact, close or miss
+    lookup TEXT NOT NULL,
+    time INTEGER NOT NULL
+);
+
+CREATE INDEX faq_usage_server_time ON faq_usage (server_id, time);
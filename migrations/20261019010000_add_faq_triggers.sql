@@ -0,0 +1,16 @@
+-- Keywords or regexes in chat messages that suggest an FAQ entry
+CREATE TABLE faq_triggers (
+    server_id BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    -- keyword or regex
+    kind TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    PRIMARY KEY (server_id, title, pattern)
+);
+
+-- Channels where FAQ entries are suggested
+CREATE TABLE faq_suggestion_channels (
+    server_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    PRIMARY KEY (server_id, channel_id)
+);
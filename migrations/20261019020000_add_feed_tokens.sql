@@ -0,0 +1,2 @@
+-- Secret part of the feed URLs of a server, set when the URL is first requested
+ALTER TABLE servers ADD COLUMN feed_token TEXT;
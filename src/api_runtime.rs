@@ -300,6 +300,19 @@ pub async fn update_api_cache(
     Ok(())
 }
 
+/// Documentation link of a class, or of a method or attribute of it, if it exists
+pub fn class_link(api: &RuntimeApiResponse, class_name: &str, member: Option<&str>) -> Option<String> {
+    let class = api.classes.iter().find(|c| c.common.name == class_name)?;
+    let url = format!("https://lua-api.factorio.com/latest/classes/{class_name}.html");
+    let Some(member) = member else {
+        return Some(url)
+    };
+    let exists = class.methods.iter().map(|m| &m.common.name)
+        .chain(class.attributes.iter().map(|a| &a.common.name))
+        .any(|name| name == member);
+    exists.then(|| format!("{url}#{member}"))
+}
+
 pub async fn get_runtime_api() -> Result<RuntimeApiResponse, Error> {
     let response = reqwest::get("https://lua-api.factorio.com/latest/runtime-api.json").await?;
 
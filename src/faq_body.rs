@@ -0,0 +1,217 @@
+use poise::serenity_prelude::{Colour, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+use crate::{Error, custom_errors::CustomError, SEPARATOR};
+
+// Discord limits for a single message
+const MAX_FIELDS: usize = 25;
+const MAX_IMAGES: usize = 10;
+const MAX_BUTTONS: usize = 25;
+const BUTTONS_PER_ROW: usize = 5;
+const MAX_EMBED_TEXT: usize = 6000;
+// Kept free for the title and the shared namespace shown as author, which depend on where an entry is shown
+const RESERVED_EMBED_TEXT: usize = 2 * 256;
+
+/// Sections, extra images, footer and link buttons of an FAQ entry, stored as JSON in `faq.body`.
+/// The text and first image of an entry stay in `faq.contents` and `faq.image`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct FaqBody {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FaqField>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buttons: Vec<FaqButton>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FaqField {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FaqButton {
+    pub label: String,
+    pub url: String,
+}
+
+impl FaqBody {
+    /// Read a body from the database, entries without one have `NULL`
+    pub fn from_json(json: Option<&str>) -> Option<Self> {
+        json.and_then(|j| serde_json::from_str(j).ok())
+    }
+
+    /// JSON to store, `None` if there is nothing to store
+    pub fn to_json(&self) -> Result<Option<String>, Error> {
+        if self == &Self::default() {
+            return Ok(None)
+        }
+        Ok(Some(serde_json::to_string(self)?))
+    }
+
+    /// Add the sections and footer to `embed`, followed by an embed for each extra image,
+    /// and the link buttons.
+    pub fn render(&self, mut embed: CreateEmbed, colour: Colour) -> (Vec<CreateEmbed>, Vec<CreateActionRow>) {
+        for field in &self.fields {
+            embed = embed.field(&field.name, &field.value, false);
+        }
+        if let Some(footer) = &self.footer {
+            embed = embed.footer(CreateEmbedFooter::new(footer));
+        }
+        let embeds = std::iter::once(embed)
+            .chain(self.images.iter().map(|url| CreateEmbed::new().image(url).colour(colour)))
+            .collect();
+        let components = self.buttons
+            .chunks(BUTTONS_PER_ROW)
+            .map(|row| CreateActionRow::Buttons(row.iter()
+                .map(|b| CreateButton::new_link(&b.url).label(&b.label))
+                .collect()))
+            .collect();
+        (embeds, components)
+    }
+
+    /// Sections as edited in the modal, each starting with a `# Name` line
+    pub fn fields_text(&self) -> String {
+        let mut text = String::new();
+        for field in &self.fields {
+            let _ = write!(text, "# {}\n{}\n\n", field.name, field.value);
+        }
+        text.trim_end().to_owned()
+    }
+
+    /// Buttons as edited in the modal, one `Label | link` per line
+    pub fn buttons_text(&self) -> String {
+        self.buttons.iter()
+            .map(|b| format!("{} {SEPARATOR} {}", b.label, b.url))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+fn is_link(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// Parse sections written as a `# Name` line followed by the section text.
+pub fn parse_fields(text: &str) -> Result<Vec<FaqField>, Error> {
+    let mut fields: Vec<FaqField> = Vec::new();
+    for line in text.lines() {
+        if let Some(name) = line.strip_prefix('#') {
+            fields.push(FaqField { name: name.trim().to_owned(), value: String::new() });
+            continue;
+        }
+        let Some(field) = fields.last_mut() else {
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Err(Box::new(CustomError::new("Sections must start with a line like `# Section name`")))
+        };
+        field.value.push_str(line);
+        field.value.push('\n');
+    }
+    for field in &mut fields {
+        field.value = field.value.trim().to_owned();
+        if field.name.is_empty() || field.name.chars().count() > 256 {
+            return Err(Box::new(CustomError::new("Section names must be between 1 and 256 characters long")))
+        }
+        if field.value.is_empty() || field.value.chars().count() > 1024 {
+            return Err(Box::new(CustomError::new(&format!("Section {} must have between 1 and 1024 characters of text", field.name))))
+        }
+    }
+    if fields.len() > MAX_FIELDS {
+        return Err(Box::new(CustomError::new(&format!("An FAQ entry can have at most {MAX_FIELDS} sections"))))
+    }
+    Ok(fields)
+}
+
+/// Check that the text, sections and footer of an entry fit in one message.
+/// Discord rejects messages with more than 6000 characters over all embeds, even if each part is within its own limit.
+pub fn check_length(contents: Option<&str>, body: &FaqBody) -> Result<(), Error> {
+    let length = contents.map_or(0, |c| c.chars().count())
+        + body.fields.iter().map(|f| f.name.chars().count() + f.value.chars().count()).sum::<usize>()
+        + body.footer.as_ref().map_or(0, |f| f.chars().count());
+    let limit = MAX_EMBED_TEXT - RESERVED_EMBED_TEXT;
+    if length > limit {
+        return Err(Box::new(CustomError::new(&format!(
+            "This FAQ entry would have {length} characters of text, sections and footer, but at most {limit} fit in a message. Shorten some of it first."
+        ))))
+    }
+    Ok(())
+}
+
+/// Parse one image link per line.
+pub fn parse_images(text: &str) -> Result<Vec<String>, Error> {
+    let images = text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_owned)
+        .collect::<Vec<String>>();
+    if let Some(invalid) = images.iter().find(|url| !is_link(url)) {
+        return Err(Box::new(CustomError::new(&format!("{invalid} is not a link to an image"))))
+    }
+    if images.len() > MAX_IMAGES {
+        return Err(Box::new(CustomError::new(&format!("An FAQ entry can have at most {MAX_IMAGES} images"))))
+    }
+    Ok(images)
+}
+
+/// Parse one `Label | link` button per line.
+pub fn parse_buttons(text: &str) -> Result<Vec<FaqButton>, Error> {
+    let mut buttons = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let Some((label, url)) = line.split_once(SEPARATOR) else {
+            return Err(Box::new(CustomError::new(&format!("Expected `Label {SEPARATOR} link` for button {line}"))))
+        };
+        let (label, url) = (label.trim(), url.trim());
+        if label.is_empty() || label.chars().count() > 80 {
+            return Err(Box::new(CustomError::new("Button labels must be between 1 and 80 characters long")))
+        }
+        if !is_link(url) {
+            return Err(Box::new(CustomError::new(&format!("{url} is not a link"))))
+        }
+        buttons.push(FaqButton { label: label.to_owned(), url: url.to_owned() });
+    }
+    if buttons.len() > MAX_BUTTONS {
+        return Err(Box::new(CustomError::new(&format!("An FAQ entry can have at most {MAX_BUTTONS} buttons"))))
+    }
+    Ok(buttons)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parses_modal_text() {
+        let text = "# Installing\nDownload the mod\n\nfrom the portal\n#Updating\nUse the in-game browser";
+        let fields = parse_fields(text).unwrap();
+        assert_eq!(fields, vec![
+            FaqField { name: "Installing".to_owned(), value: "Download the mod\n\nfrom the portal".to_owned() },
+            FaqField { name: "Updating".to_owned(), value: "Use the in-game browser".to_owned() },
+        ]);
+        assert!(parse_fields("No heading").is_err());
+
+        let buttons = parse_buttons("Wiki | https://wiki.factorio.com\n\nPortal|https://mods.factorio.com").unwrap();
+        assert_eq!(buttons[1], FaqButton { label: "Portal".to_owned(), url: "https://mods.factorio.com".to_owned() });
+        assert!(parse_buttons("Wiki | wiki.factorio.com").is_err());
+
+        let body = FaqBody { fields, buttons, ..Default::default() };
+        assert_eq!(parse_fields(&body.fields_text()).unwrap(), body.fields);
+        assert_eq!(parse_buttons(&body.buttons_text()).unwrap(), body.buttons);
+        assert_eq!(FaqBody::default().to_json().unwrap(), None);
+    }
+
+    #[test]
+    fn checks_total_length() {
+        let field = FaqField { name: "n".repeat(256), value: "v".repeat(1024) };
+        let body = FaqBody { fields: vec![field; 4], footer: Some("f".repeat(100)), ..Default::default() };
+        assert!(check_length(Some(&"c".repeat(260)), &body).is_ok());
+        assert!(check_length(Some(&"c".repeat(270)), &body).is_err());
+        assert!(check_length(Some(&"ä".repeat(4000)), &FaqBody::default()).is_ok());
+    }
+}
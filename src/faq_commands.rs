@@ -1,29 +1,49 @@
-use sqlx::{Pool, Sqlite};
-use std::sync::{Arc, RwLock};
+use sqlx::{Pool, Sqlite, SqliteConnection};
+use std::{fmt::Write, sync::{Arc, RwLock}};
 use poise::serenity_prelude as serenity;
 use poise::CreateReply;
 use log::error;
 
-use crate::{Context, Error, custom_errors::CustomError, util, util::is_mod, SEPARATOR};
+use crate::{Context, Data, Error, custom_errors::CustomError, faq_body::{self, FaqBody}, faq_placeholders, faq_sharing, faq_stats::{self, FaqLookup}, faq_suggest, util, util::is_mod, SEPARATOR};
 
 #[derive(Debug, Clone)]
 pub struct FaqCacheEntry {
     server_id: i64,
     title: String,
+    /// Published to a namespace the server subscribes to
+    shared: bool,
 }
 
 struct FaqEntry {
     title: String,
     contents: Option<String>,
     image: Option<String>,
-    link: Option<String>,
+    body: Option<String>,
+}
+
+/// Everything shown for an FAQ entry besides its title
+#[derive(Debug, Clone, Default)]
+pub struct FaqContent {
+    pub contents: Option<String>,
+    pub image: Option<String>,
+    /// JSON of a [`FaqBody`]
+    pub body: Option<String>,
 }
 
 pub async fn update_faq_cache(
     cache: Arc<RwLock<Vec<FaqCacheEntry>>>,
     db: Pool<Sqlite>
 ) -> Result<(), Error> {
-    let records = sqlx::query_as!(FaqCacheEntry, r#"SELECT server_id, title FROM faq"#)
+    let records = sqlx::query_as!(FaqCacheEntry, r#"
+        SELECT server_id AS "server_id!", title AS "title!", FALSE AS "shared!: bool" FROM faq
+        UNION ALL
+        SELECT server_id, alias AS "title!", FALSE AS "shared!: bool" FROM faq_aliases
+        UNION ALL
+        SELECT s.server_id, e.title AS "title!", TRUE AS "shared!: bool"
+        FROM faq_namespace_subscriptions s
+        JOIN faq_namespaces n ON n.name = s.namespace
+        JOIN faq_namespace_entries e ON e.namespace = n.name
+        JOIN faq ON faq.server_id = n.server_id AND faq.title = e.title"#)
         .fetch_all(&db)
         .await?;
 
@@ -50,7 +70,7 @@ pub fn faq() -> poise::Command<crate::Data, Box<dyn std::error::Error + Send + S
 pub async fn faq_slash(
     ctx: Context<'_>,
     #[description = "Name of the faq entry"]
-    #[autocomplete = "autocomplete_faq"]
+    #[autocomplete = "autocomplete_faq_or_shared"]
     name: String,
 ) -> Result<(), Error> {
     faq_core(ctx, name).await?;
@@ -74,24 +94,59 @@ pub async fn faq_prefix(
     Ok(())
 }
 
+// Titles listed on a single page of the FAQ index
+const INDEX_TITLES_PER_PAGE: usize = 40;
+
+/// Group titles by category into pages of at most `per_page` titles, sorted by category and title.
+/// Titles without a category come last.
+pub fn index_pages(mut entries: Vec<(String, Option<String>)>, per_page: usize) -> Vec<(String, Vec<String>)> {
+    entries.sort_by(|(title_a, cat_a), (title_b, cat_b)| {
+        (cat_a.is_none(), cat_a, title_a).cmp(&(cat_b.is_none(), cat_b, title_b))
+    });
+    let mut pages: Vec<(String, Vec<String>)> = Vec::new();
+    let mut current_category = None;
+    for (title, category) in entries {
+        let category = category.unwrap_or_else(|| "Uncategorized".to_owned());
+        match pages.last_mut() {
+            Some((_, titles)) if current_category.as_ref() == Some(&category) && titles.len() < per_page => titles.push(title),
+            _ => {
+                current_category = Some(category.clone());
+                pages.push((category, vec![title]));
+            },
+        }
+    }
+    pages
+}
+
 async fn list_faqs(
     ctx: Context<'_>,
 ) -> Result<(), Error> {
     let db = &ctx.data().database;
     let server_id = util::get_server_id(ctx)?;
-    let db_entries = sqlx::query!(r#"SELECT title FROM faq WHERE server_id = $1"#, server_id)
+    let db_entries = sqlx::query!(r#"SELECT title, category FROM faq WHERE server_id = $1"#, server_id)
         .fetch_all(db)
-        .await?;
-    let mut faq_names = db_entries.iter().map(|f| f.title.clone()).collect::<Vec<String>>();
-    faq_names.sort();
+        .await?
+        .into_iter()
+        .map(|f| (f.title, f.category))
+        .collect::<Vec<(String, Option<String>)>>();
+    let pages = index_pages(db_entries, INDEX_TITLES_PER_PAGE);
+    if pages.is_empty() {
+        return Err(Box::new(CustomError::new("This server has no FAQ entries yet")))
+    }
+
     let color = serenity::Colour::GOLD;
-    let embed = serenity::CreateEmbed::new()
-        .title("List of FAQ tags")
-        .description(faq_names.join(", "))
-        .color(color);
-    let builder = CreateReply::default().embed(embed);
-    ctx.send(builder).await?;
-    Ok(())
+    let mut sections: Vec<(String, usize)> = Vec::new();
+    let mut embeds = Vec::new();
+    for (index, (category, titles)) in pages.into_iter().enumerate() {
+        if sections.last().is_none_or(|(name, _)| *name != category) {
+            sections.push((category.clone(), index));
+        }
+        embeds.push(serenity::CreateEmbed::new()
+            .title(format!("List of FAQ tags: {category}"))
+            .description(titles.join(", "))
+            .color(color));
+    }
+    util::paginate_sections(ctx, embeds, sections).await
 }
 
 async fn faq_core(
@@ -102,70 +157,110 @@ async fn faq_core(
     let name_lc = util::capitalize(&command.to_lowercase());
     let db = &ctx.data().database;
     let server_id = util::get_server_id(ctx)?;
+    let time = ctx.created_at().timestamp();
 
-    // Find entry matching given `name`
-    let entry_option = sqlx::query_as!(FaqEntry, r#"
-        SELECT title, contents, image, link FROM faq WHERE title = $1 AND server_id = $2"#, 
-        name_lc, server_id
-    )
-        .fetch_optional(db)
-        .await?;
-
-    // Check if entry found
-    let (entry, close_match) = if let Some(e) = entry_option { (e, false) } else {
-        // If no entry found, check for near matches
-        let closest_match = find_closest_faq(ctx, &name_lc, server_id)?;
-        if let Some(match_name) = closest_match {
-            (get_faq_entry(db, server_id, &match_name).await?, true)
-        } else {
-            // If no near matches, return no results message
-            let errmsg = format!(
-                "Could not find {name_lc} or any similarly tags in FAQ tags. 
-                Would you like to search [the wiki](https://wiki.factorio.com/index.php?search={})?", name_lc.replace(' ', "%20"));
-            return Err(Box::new(CustomError::new(&errmsg)));
+    // Find entry matching given `name`, falling back to near matches
+    let mut found = find_faq_or_shared(db, server_id, &name_lc).await?;
+    let mut close_match = false;
+    if found.is_none() {
+        if let Some(match_name) = find_closest_faq(ctx, &name_lc, server_id)? {
+            found = find_faq_or_shared(db, server_id, &match_name).await?;
+            close_match = true;
         }
+    }
+    let Some((title, mut content, namespace)) = found else {
+        faq_stats::log_faq_usage(db, server_id, &name_lc, None, FaqLookup::Miss, time).await;
+        // If no near matches, return no results message
+        let errmsg = format!(
+            "Could not find {name_lc} or any similarly tags in FAQ tags. 
+            Would you like to search [the wiki](https://wiki.factorio.com/index.php?search={})?", name_lc.replace(' ', "%20"));
+        return Err(Box::new(CustomError::new(&errmsg)));
     };
 
-    // If link to other entry found, get other entry
-    let entry_final: FaqEntry = match entry.link {
-        None => entry,
-        Some(entry_link) => {
-            get_faq_entry(db, server_id, &entry_link).await?
-        }
-    };
+    let lookup = if close_match { FaqLookup::Close } else { FaqLookup::Exact };
+    faq_stats::log_faq_usage(db, server_id, &name_lc, Some((&title, namespace.as_deref())), lookup, time).await;
 
     // Make and send embed for found entry
-    let color = serenity::Colour::GOLD;
     let title = if close_match {
-        format!(r#"Could not find "{name_lc}" in FAQ tags. Did you mean "{}"?"#, entry_final.title)
+        format!(r#"Could not find "{name_lc}" in FAQ tags. Did you mean "{title}"?"#)
     } else {
-        entry_final.title
+        title
     };
+    if let Some(contents) = content.contents.as_ref().filter(|c| faq_placeholders::has_placeholders(c)) {
+        // Placeholders may need web requests, which can take longer than Discord waits for a response
+        ctx.defer().await?;
+        content.contents = Some(faq_placeholders::resolve_placeholders(ctx.data(), contents).await);
+    }
+    let mut embed = serenity::CreateEmbed::new().title(title);
+    if let Some(namespace) = namespace {
+        embed = embed.author(serenity::CreateEmbedAuthor::new(format!("Shared FAQ from {namespace}")));
+    }
+    ctx.send(render_faq_embed(embed, content)).await?;
+    Ok(())
+}
 
-    let mut embed = serenity::CreateEmbed::new()
-        .title(title)
-        .color(color);
-    if let Some(content) = entry_final.contents {
-        embed = embed.description(content);
+/// Entry `name` of this server, or else of a namespace it subscribes to, with the namespace it came from
+pub async fn find_faq_or_shared(db: &Pool<Sqlite>, server_id: i64, name: &str) -> Result<Option<(String, FaqContent, Option<String>)>, Error> {
+    if let Some(entry) = find_faq_entry(db, server_id, name).await? {
+        let content = FaqContent { contents: entry.contents, image: entry.image, body: entry.body };
+        return Ok(Some((entry.title, content, None)))
     }
+    Ok(faq_sharing::find_shared_faq(db, server_id, name)
+        .await?
+        .map(|faq_sharing::SharedFaqEntry { namespace, title, contents, image, body }| {
+            (title, FaqContent { contents, image, body }, Some(namespace))
+        }))
+}
+
+/// Embeds and link buttons showing an FAQ entry
+pub fn render_faq(title: String, content: FaqContent) -> CreateReply {
+    render_faq_embed(serenity::CreateEmbed::new().title(title), content)
+}
 
-    if let Some(img) = entry_final.image {
+/// Like [`render_faq`], starting from an embed that already has a title
+pub fn render_faq_embed(embed: serenity::CreateEmbed, content: FaqContent) -> CreateReply {
+    let color = serenity::Colour::GOLD;
+    let mut embed = embed.color(color);
+    if let Some(contents) = content.contents {
+        embed = embed.description(contents);
+    }
+    if let Some(img) = content.image {
         embed = embed.image(img);
     }
-
-    let builder = CreateReply::default().embed(embed);
-    ctx.send(builder).await?;
-    Ok(())
+    let Some(body) = FaqBody::from_json(content.body.as_deref()) else {
+        return CreateReply::default().embed(embed)
+    };
+    let (embeds, components) = body.render(embed, color);
+    let mut reply = CreateReply::default().components(components);
+    for embed in embeds {
+        reply = reply.embed(embed);
+    }
+    reply
 }
 
-async fn get_faq_entry(db: &Pool<Sqlite>, server_id: i64, name: &str) -> Result<FaqEntry, Error> {
+// Entry with the title `name`, or the entry `name` is an alias of
+async fn find_faq_entry(db: &Pool<Sqlite>, server_id: i64, name: &str) -> Result<Option<FaqEntry>, Error> {
     Ok(sqlx::query_as!(FaqEntry, r#"
-        SELECT title, contents, image, link FROM faq WHERE title = $1 AND server_id = $2"#, 
+        SELECT title, contents, image, body FROM faq
+        WHERE server_id = $2 AND title = COALESCE((SELECT title FROM faq_aliases WHERE server_id = $2 AND alias = $1), $1)"#,
         name, server_id
     )
         .fetch_optional(db)
-        .await?.map_or_else(|| Err(Box::new(CustomError::new(&format!("Could not get FAQ entry {name} from database")))), Ok)?
-    )
+        .await?)
+}
+
+async fn get_faq_entry(db: &Pool<Sqlite>, server_id: i64, name: &str) -> Result<FaqEntry, Error> {
+    find_faq_entry(db, server_id, name)
+        .await?
+        .ok_or_else(|| -> Error { Box::new(CustomError::new(&format!("Could not get FAQ entry {name} from database"))) })
+}
+
+/// Entry title that `alias` is an alias of
+pub async fn get_alias_target(db: &Pool<Sqlite>, server_id: i64, alias: &str) -> Result<Option<String>, Error> {
+    Ok(sqlx::query!(r#"SELECT title FROM faq_aliases WHERE server_id = $1 AND alias = $2"#, server_id, alias)
+        .fetch_optional(db)
+        .await?
+        .map(|a| a.title))
 }
 
 fn find_closest_faq(ctx: Context<'_>, name: &str, server_id: i64) -> Result<Option<String>, Error> {
@@ -185,11 +280,29 @@ fn find_closest_faq(ctx: Context<'_>, name: &str, server_id: i64) -> Result<Opti
     )
 }
 
-#[allow(clippy::unused_async, clippy::cast_possible_wrap)]
-async fn autocomplete_faq<'a>(
+/// Titles and aliases of this server starting with `partial`
+#[allow(clippy::unused_async)]
+pub async fn autocomplete_faq(
+    ctx: Context<'_>,
+    partial: &str,
+) -> Vec<String> {
+    faq_titles(ctx, partial, false)
+}
+
+#[allow(clippy::unused_async)]
+async fn autocomplete_faq_or_shared(
     ctx: Context<'_>,
-    partial: &'a str,
-) -> Vec<String>{
+    partial: &str,
+) -> Vec<String> {
+    faq_titles(ctx, partial, true)
+}
+
+#[allow(clippy::cast_possible_wrap)]
+fn faq_titles(
+    ctx: Context<'_>,
+    partial: &str,
+    include_shared: bool,
+) -> Vec<String> {
     let Some(server) = ctx.guild_id() else {
         error!("Could not get server ID while autocompleting faq name"); 
         return vec![]
@@ -204,102 +317,374 @@ async fn autocomplete_faq<'a>(
         },
     };
     faqcache.iter()
-        .filter(|f| f.server_id == server_id && f.title.to_lowercase().starts_with(&partial.to_lowercase()))
+        .filter(|f| f.server_id == server_id && (include_shared || !f.shared) && f.title.to_lowercase().starts_with(&partial.to_lowercase()))
         .map(|f| f.title.clone())
         .collect::<Vec<String>>()
 }
 
-/// Add, remove or link FAQ entries
+struct FaqRevision {
+    revision: i64,
+    contents: Option<String>,
+    image: Option<String>,
+    body: Option<String>,
+    edit_time: i64,
+    author: i64,
+}
+
+/// Store an FAQ entry, replacing the contents of any entry with the same title, and record it as a new revision.
+/// Returns the revision number.
+pub async fn save_faq(
+    db: &Pool<Sqlite>,
+    server_id: i64,
+    title: &str,
+    content: &FaqContent,
+    edit_time: i64,
+    author: i64,
+) -> Result<i64, Error> {
+    let mut transaction = db.begin().await?;
+    let revision = save_faq_in(&mut transaction, server_id, title, content, edit_time, author).await?;
+    transaction.commit().await?;
+    Ok(revision)
+}
+
+/// [`save_faq`] as part of a larger transaction
+pub async fn save_faq_in(
+    transaction: &mut SqliteConnection,
+    server_id: i64,
+    title: &str,
+    content: &FaqContent,
+    edit_time: i64,
+    author: i64,
+) -> Result<i64, Error> {
+    let FaqContent { contents, image, body } = content;
+    // Update in place rather than replacing the row, so settings like the category are kept
+    let updated = sqlx::query!(r#"UPDATE faq SET contents = $3, image = $4, body = $5, edit_time = $6, author = $7
+        WHERE server_id = $1 AND title = $2"#, server_id, title, contents, image, body, edit_time, author)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+    if updated == 0 {
+        sqlx::query!(r#"INSERT INTO faq (server_id, title, contents, image, body, edit_time, author)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)"#, server_id, title, contents, image, body, edit_time, author)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    let revision = sqlx::query!(r#"SELECT COALESCE(MAX(revision), 0) + 1 AS "revision!: i64" FROM faq_revisions
+        WHERE server_id = $1 AND title = $2"#, server_id, title)
+        .fetch_one(&mut *transaction)
+        .await?
+        .revision;
+    sqlx::query!(r#"INSERT INTO faq_revisions (server_id, title, revision, contents, image, body, edit_time, author)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#, server_id, title, revision, contents, image, body, edit_time, author)
+        .execute(&mut *transaction)
+        .await?;
+    Ok(revision)
+}
+
+/// Lines removed from `old` prefixed with `-` and lines added in `new` prefixed with `+`, in order.
+pub fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let old = old.lines().collect::<Vec<&str>>();
+    let new = new.lines().collect::<Vec<&str>>();
+    // Length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(format!("- {}", old[i]));
+            i += 1;
+        } else {
+            diff.push(format!("+ {}", new[j]));
+            j += 1;
+        }
+    }
+    diff.extend(old[i..].iter().map(|line| format!("- {line}")));
+    diff.extend(new[j..].iter().map(|line| format!("+ {line}")));
+    diff
+}
+
+// Diff of a revision against the one before it, shortened to fit an embed
+fn describe_revision(previous: Option<&FaqRevision>, revision: &FaqRevision) -> String {
+    let mut description = format!("Edited by <@{}> <t:{}:f>", revision.author, revision.edit_time);
+    if previous.and_then(|p| p.image.as_deref()) != revision.image.as_deref() {
+        description.push_str(if revision.image.is_some() { "\nImage changed" } else { "\nImage removed" });
+    }
+    if previous.and_then(|p| p.body.as_deref()) != revision.body.as_deref() {
+        description.push_str("\nSections, images or buttons changed");
+    }
+    let old_contents = previous.and_then(|p| p.contents.as_deref()).unwrap_or_default();
+    let diff = line_diff(old_contents, revision.contents.as_deref().unwrap_or_default());
+    if diff.is_empty() {
+        description.push_str("\nNo changes to the text");
+        return description
+    }
+    description.push_str("\n```diff\n");
+    let mut length = 0;
+    for (shown, line) in diff.iter().enumerate() {
+        length += line.len() + 1;
+        if length > 3800 {
+            let _ = writeln!(description, "... {} more lines", diff.len() - shown);
+            break;
+        }
+        // Keep lines from closing the code block early
+        description.push_str(&line.replace("```", "` ` `"));
+        description.push('\n');
+    }
+    description.push_str("```");
+    description
+}
+
+async fn get_faq_revisions(db: &Pool<Sqlite>, server_id: i64, title: &str) -> Result<Vec<FaqRevision>, Error> {
+    let revisions = sqlx::query_as!(FaqRevision, r#"SELECT revision, contents, image, body, edit_time, author FROM faq_revisions
+        WHERE server_id = $1 AND title = $2 ORDER BY revision"#, server_id, title)
+        .fetch_all(db)
+        .await?;
+    if revisions.is_empty() {
+        return Err(Box::new(CustomError::new(&format!("No earlier versions of FAQ entry {title} found"))))
+    }
+    Ok(revisions)
+}
+
+// Search hits listed on a single page of /faq_search
+const SEARCH_RESULTS_PER_PAGE: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaqSearchHit {
+    pub title: String,
+    pub snippet: String,
+}
+
+// Turn free text into an FTS5 query matching entries that contain every word, or words starting with it
+fn fts_query(query: &str) -> Option<String> {
+    let terms = query.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\"*"))
+        .collect::<Vec<String>>();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// FAQ entries matching every word of `query`, best matches first.
+/// Titles weigh most, followed by aliases, contents and sections.
+pub async fn search_faqs(db: &Pool<Sqlite>, server_id: i64, query: &str) -> Result<Vec<FaqSearchHit>, Error> {
+    let Some(fts_query) = fts_query(query) else {
+        return Ok(Vec::new())
+    };
+    Ok(sqlx::query_as!(FaqSearchHit, r#"
+        SELECT title AS "title!: String", snippet(faq_search, -1, '**', '**', '...', 16) AS "snippet!: String"
+        FROM faq_search
+        WHERE faq_search MATCH $1 AND server_id = $2
+        ORDER BY bm25(faq_search, 10.0, 5.0, 1.0, 1.0, 0.0)
+        LIMIT 100"#, fts_query, server_id)
+        .fetch_all(db)
+        .await?)
+}
+
+/// Search the text of all FAQ entries
 #[allow(clippy::unused_async)]
-#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings", subcommands("new", "remove", "link"), aliases("faq-edit", "faqedit"), subcommand_required)]
+#[poise::command(prefix_command, slash_command, guild_only, aliases("faq-search", "faqsearch"))]
+pub async fn faq_search(
+    ctx: Context<'_>,
+    #[description = "Words to search for"]
+    #[rest]
+    query: String,
+) -> Result<(), Error> {
+    let server_id = util::get_server_id(ctx)?;
+    let hits = search_faqs(&ctx.data().database, server_id, &query).await?;
+    if hits.is_empty() {
+        return Err(Box::new(CustomError::new(&format!("No FAQ entries found for {query}"))))
+    }
+    let title = format!("FAQ entries matching \"{query}\"");
+    let pages = hits.chunks(SEARCH_RESULTS_PER_PAGE)
+        .map(|chunk| {
+            let description = chunk.iter()
+                .map(|hit| format!("**{}**\n{}", hit.title, hit.snippet.replace('\n', " ")))
+                .collect::<Vec<String>>()
+                .join("\n\n");
+            serenity::CreateEmbed::new()
+                .title(&title)
+                .description(description)
+                .colour(serenity::Colour::GOLD)
+        })
+        .collect::<Vec<serenity::CreateEmbed>>();
+    util::paginate_embeds(ctx, pages).await
+}
+
+/// Add, remove or edit FAQ entries
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings", subcommands("new", "edit", "rich", "remove", "alias", "category", "history", "revert", "faq_stats::stats", "faq_suggest::trigger", "faq_suggest::suggestions", "faq_sharing::share"), aliases("faq-edit", "faqedit"), subcommand_required)]
 pub async fn faq_edit(
     _ctx: Context<'_>
 ) -> Result<(), Error> {
     Ok(())
 }
 
-/// Add an faq entry
-#[allow(clippy::unused_async, clippy::cast_possible_wrap)]
-#[poise::command(prefix_command, slash_command, guild_only, aliases("edit", "add"))]
+#[derive(Debug, poise::Modal)]
+#[name = "FAQ entry"]
+struct FaqModal {
+    #[name = "Title"]
+    #[max_length = 256]
+    title: String,
+    #[name = "Contents"]
+    #[placeholder = "Placeholders like {latest_fff}, {mod:<name>.version}, {api:<Class>} or {wiki:<page>} stay up to date"]
+    #[paragraph]
+    #[max_length = 4000]
+    contents: Option<String>,
+}
+
+/// Add an faq entry. Opens an editor when used as a slash command without contents.
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, aliases("add"))]
 pub async fn new(
     ctx: Context<'_>,
     #[description = "Name of the faq"]
     name: String,
     #[description = "Link to an image."]
     image: Option<serenity::Attachment>,
-    #[description = "Contents of the FAQ"]
+    #[description = "Contents of the FAQ, leave empty to write them in an editor"]
     #[rest]
     content: Option<String>,
 ) -> Result<(), Error> {
     let name_lc = util::capitalize(&name.to_lowercase());
-    let Some(server) = ctx.guild_id() else {
-        return Err(Box::new(CustomError::new("Could not get server ID")))
-    };
-    let server_id = server.get() as i64;
+    let server_id = util::get_server_id(ctx)?;
     let db = &ctx.data().database;
-    // Check if name already exists
-    let pre_existing =  sqlx::query!(r#"SELECT title FROM faq WHERE server_id = $1 AND title = $2"#, server_id, name_lc)
+    let existing = sqlx::query!(r#"SELECT contents FROM faq WHERE server_id = $1 AND title = $2"#, server_id, name_lc)
         .fetch_optional(db)
-        .await?
-        .is_some();
-
-    let mut response: Option<poise::ReplyHandle> = None;
-
-    // If image attached, re-upload image to generate a non-ephemeral link for storage
-    let attachment_url = if let Some(att) = image {
-        if att.ephemeral {
-            let attachment = serenity::CreateAttachment::url(ctx.http(), &att.url).await?;
-            let embed = serenity::CreateEmbed::new()
-                .title(format!("Adding FAQ entry: {name_lc}"))
-                .description("Uploading image to Discord...")
-                .colour(serenity::Colour::DARK_GREEN)
-                .attachment(att.filename);
-            let builder = CreateReply::default().attachment(attachment).embed(embed);
-            let r = ctx.send(builder).await?;
-            let message = r.message().await?;
-            response = Some(r.clone());
-            
-            let Some(message_embed) = message.embeds.first() else {
-                return Err(Box::new(CustomError::new("Could not create FAQ entry: embed not found")))
+        .await?;
+
+    let (title, content) = match ctx {
+        poise::Context::Application(app_ctx) if content.is_none() => {
+            let defaults = FaqModal {
+                title: name_lc.clone(),
+                contents: existing.as_ref().and_then(|e| e.contents.clone()),
             };
-            let Some(ref embed_image) = message_embed.image else {
-                return Err(Box::new(CustomError::new("Could not create FAQ entry: image not found in embed")))
+            let Some(modal) = poise::execute_modal(app_ctx, Some(defaults), None).await? else {
+                return Ok(())
             };
-            Some(embed_image.url.clone())
-        } else {
-            Some(att.url.clone())
-        }
-    } else {
-        None
+            (util::capitalize(&modal.title.trim().to_lowercase()), modal.contents)
+        },
+        _ => (name_lc.clone(), content),
     };
+    let original = existing.map(|_| name_lc);
+    store_faq(ctx, server_id, original, title, content, image).await
+}
 
-    let timestamp = ctx.created_at().timestamp();
-    let author_id = ctx.author().id.get() as i64;
-
-    // Delete previous entry to prevent duplication
-    if pre_existing {
-        sqlx::query!(r#"DELETE FROM faq WHERE server_id = $1 AND title = $2"#, server_id, name_lc)
-            .execute(db)
-            .await?;
+/// Edit the title and contents of an faq entry
+#[poise::command(slash_command, guild_only)]
+pub async fn edit(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    #[description = "FAQ entry to edit"]
+    #[autocomplete = "autocomplete_faq"]
+    name: String,
+    #[description = "New image, keeps the current image if left empty"]
+    image: Option<serenity::Attachment>,
+) -> Result<(), Error> {
+    let name_lc = util::capitalize(&name.to_lowercase());
+    let server_id = util::get_server_id(ctx.into())?;
+    let Some(entry) = find_faq_entry(&ctx.data().database, server_id, &name_lc).await? else {
+        return Err(Box::new(CustomError::new(&format!("Could not find FAQ entry {name_lc}"))))
     };
-    sqlx::query!(r#"INSERT INTO faq (server_id, title, contents, image, edit_time, author)
-    VALUES ($1, $2, $3, $4, $5, $6)"#, server_id, name_lc, content, attachment_url, timestamp, author_id)
-        .execute(db)
-        .await?;
+    let defaults = FaqModal { title: entry.title.clone(), contents: entry.contents };
+    let Some(modal) = poise::execute_modal(ctx, Some(defaults), None).await? else {
+        return Ok(())
+    };
+    let title = util::capitalize(&modal.title.trim().to_lowercase());
+    store_faq(ctx.into(), server_id, Some(entry.title), title, modal.contents, image).await
+}
 
-    let title = if pre_existing {format!(r#"Successfully edited "{name_lc}""#)}
-        else {format!(r#"Successfully added "{name_lc}" to database"#)};
+// Re-upload an image to generate a non-ephemeral link for storage.
+// Returns the link and the message used for uploading, to be edited with the result.
+async fn reupload_image<'a>(ctx: Context<'a>, title: &str, att: serenity::Attachment) -> Result<(String, Option<poise::ReplyHandle<'a>>), Error> {
+    if !att.ephemeral {
+        return Ok((att.url, None))
+    }
+    let attachment = serenity::CreateAttachment::url(ctx.http(), &att.url).await?;
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("Adding FAQ entry: {title}"))
+        .description("Uploading image to Discord...")
+        .colour(serenity::Colour::DARK_GREEN)
+        .attachment(att.filename);
+    let builder = CreateReply::default().attachment(attachment).embed(embed);
+    let r = ctx.send(builder).await?;
+    let message = r.message().await?;
 
-    let mut embed = serenity::CreateEmbed::new()
-        .title(title)
-        .colour(serenity::Colour::DARK_GREEN);
-    if let Some(c) = content {
-        embed = embed.description(c);
+    let Some(message_embed) = message.embeds.first() else {
+        return Err(Box::new(CustomError::new("Could not create FAQ entry: embed not found")))
+    };
+    let Some(ref embed_image) = message_embed.image else {
+        return Err(Box::new(CustomError::new("Could not create FAQ entry: image not found in embed")))
+    };
+    Ok((embed_image.url.clone(), Some(r)))
+}
+
+// Save an added or edited FAQ entry, renaming it if the title of the `original` entry changed.
+// The current image is kept unless a new one is attached.
+#[allow(clippy::cast_possible_wrap)]
+async fn store_faq(
+    ctx: Context<'_>,
+    server_id: i64,
+    original: Option<String>,
+    title: String,
+    content: Option<String>,
+    image: Option<serenity::Attachment>,
+) -> Result<(), Error> {
+    let db = &ctx.data().database;
+    if title.is_empty() {
+        return Err(Box::new(CustomError::new("FAQ entries need a title")))
     }
-    if let Some(url) = attachment_url {
-        embed = embed.image(url);
+    if let Some(target) = get_alias_target(db, server_id, &title).await? {
+        return Err(Box::new(CustomError::new(&format!("{title} is an alias of {target}. Edit {target} instead, or remove the alias first."))))
     }
-    let builder = CreateReply::default().embed(embed);
+    let existing_title = original.as_deref().unwrap_or(&title);
+    let existing = sqlx::query!(r#"SELECT image, body FROM faq WHERE server_id = $1 AND title = $2"#, server_id, existing_title)
+        .fetch_optional(db)
+        .await?;
+    if let Some(body) = existing.as_ref().and_then(|e| FaqBody::from_json(e.body.as_deref())) {
+        faq_body::check_length(content.as_deref(), &body)?;
+    }
+    if let Some(old_title) = original.as_deref().filter(|o| *o != title) {
+        if find_faq_entry(db, server_id, &title).await?.is_some() {
+            return Err(Box::new(CustomError::new(&format!("Error: An faq entry with title {title} already exists"))))
+        }
+        rename_faq(db, server_id, old_title, &title).await?;
+        if let Err(e) = ctx.data().faq_suggestions.reload().await {
+            error!("Error while updating faq suggestion cache: {e}");
+        }
+    }
+
+    let (image, response) = match image {
+        Some(att) => {
+            let (url, response) = reupload_image(ctx, &title, att).await?;
+            (Some(url), response)
+        },
+        None => (None, None),
+    };
+    let pre_existing = existing.is_some();
+    let (old_image, body) = existing.map_or((None, None), |e| (e.image, e.body));
+    // Sections, images and buttons are edited separately with /faq_edit rich
+    let faq_content = FaqContent {
+        contents: content,
+        image: image.or(old_image),
+        body,
+    };
+    let timestamp = ctx.created_at().timestamp();
+    let author_id = ctx.author().id.get() as i64;
+    save_faq(db, server_id, &title, &faq_content, timestamp, author_id).await?;
+
+    let reply_title = if pre_existing {format!(r#"Successfully edited "{title}""#)}
+        else {format!(r#"Successfully added "{title}" to database"#)};
+    let builder = render_faq(reply_title, faq_content);
     if let Some(r) = response {
         r.edit(ctx, builder).await?;
     } else {
@@ -308,6 +693,102 @@ pub async fn new(
     Ok(())
 }
 
+/// Move an FAQ entry to a new title, together with its aliases, revisions, triggers and published copies.
+pub async fn rename_faq(db: &Pool<Sqlite>, server_id: i64, old_title: &str, new_title: &str) -> Result<(), Error> {
+    let mut transaction = db.begin().await?;
+    sqlx::query!(r#"UPDATE faq SET title = $3 WHERE server_id = $1 AND title = $2"#, server_id, old_title, new_title)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"UPDATE faq_aliases SET title = $3 WHERE server_id = $1 AND title = $2"#, server_id, old_title, new_title)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"UPDATE faq_triggers SET title = $3 WHERE server_id = $1 AND title = $2"#, server_id, old_title, new_title)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"UPDATE faq_usage SET title = $3 WHERE server_id = $1 AND title = $2 AND namespace IS NULL"#, server_id, old_title, new_title)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"UPDATE faq_namespace_entries SET title = $3
+        WHERE title = $2 AND namespace IN (SELECT name FROM faq_namespaces WHERE server_id = $1)"#, server_id, old_title, new_title)
+        .execute(&mut *transaction)
+        .await?;
+    // Revisions left behind by a removed entry with the new title make way for the history of this one
+    sqlx::query!(r#"DELETE FROM faq_revisions WHERE server_id = $1 AND title = $2"#, server_id, new_title)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"UPDATE faq_revisions SET title = $3 WHERE server_id = $1 AND title = $2"#, server_id, old_title, new_title)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[derive(Debug, poise::Modal)]
+#[name = "Sections, images and buttons"]
+struct RichFaqModal {
+    #[name = "Sections, each starting with a # Name line"]
+    #[placeholder = "# Installing\nHow to install it\n\n# Updating\nHow to update it"]
+    #[paragraph]
+    #[max_length = 4000]
+    sections: Option<String>,
+    #[name = "Images, one link per line"]
+    #[placeholder = "The first image is shown in the main embed"]
+    #[paragraph]
+    images: Option<String>,
+    #[name = "Footer"]
+    #[max_length = 2048]
+    footer: Option<String>,
+    #[name = "Link buttons, one per line"]
+    #[placeholder = "Wiki | https://wiki.factorio.com"]
+    #[paragraph]
+    buttons: Option<String>,
+}
+
+/// Edit the sections, images, footer and link buttons of an FAQ entry
+#[allow(clippy::cast_possible_wrap)]
+#[poise::command(slash_command, guild_only)]
+pub async fn rich(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    #[description = "FAQ entry to edit"]
+    #[autocomplete = "autocomplete_faq"]
+    name: String,
+) -> Result<(), Error> {
+    let name_lc = util::capitalize(&name.to_lowercase());
+    let server_id = util::get_server_id(ctx.into())?;
+    let db = &ctx.data().database;
+    let Some(entry) = find_faq_entry(db, server_id, &name_lc).await? else {
+        return Err(Box::new(CustomError::new(&format!("Could not find FAQ entry {name_lc}. Create it with /faq_edit new first."))))
+    };
+
+    let body = FaqBody::from_json(entry.body.as_deref()).unwrap_or_default();
+    let images = entry.image.iter().chain(&body.images).cloned().collect::<Vec<String>>();
+    let defaults = RichFaqModal {
+        sections: Some(body.fields_text()),
+        images: Some(images.join("\n")),
+        footer: body.footer.clone(),
+        buttons: Some(body.buttons_text()),
+    };
+    let Some(modal) = poise::execute_modal(ctx, Some(defaults), None).await? else {
+        return Ok(())
+    };
+
+    let mut images = faq_body::parse_images(modal.images.as_deref().unwrap_or_default())?.into_iter();
+    let image = images.next();
+    let body = FaqBody {
+        fields: faq_body::parse_fields(modal.sections.as_deref().unwrap_or_default())?,
+        images: images.collect(),
+        footer: modal.footer.map(|f| f.trim().to_owned()).filter(|f| !f.is_empty()),
+        buttons: faq_body::parse_buttons(modal.buttons.as_deref().unwrap_or_default())?,
+    };
+    faq_body::check_length(entry.contents.as_deref(), &body)?;
+    let content = FaqContent { contents: entry.contents, image, body: body.to_json()? };
+    let timestamp = ctx.created_at().timestamp();
+    let author_id = ctx.author().id.get() as i64;
+    save_faq(db, server_id, &entry.title, &content, timestamp, author_id).await?;
+    ctx.send(render_faq(format!(r#"Successfully edited "{}""#, entry.title), content)).await?;
+    Ok(())
+}
+
 /// Remove an faq entry
 #[allow(clippy::unused_async, clippy::cast_possible_wrap)]
 #[poise::command(prefix_command, slash_command, guild_only, aliases("delete"))]
@@ -323,66 +804,305 @@ pub async fn remove(
     };
     let server_id = server.get() as i64;
     let db = &ctx.data().database;
-    match sqlx::query!(r#"DELETE FROM faq WHERE server_id = $1 AND (title = $2 OR link = $2)"#, server_id, name_lc)
-        .execute(db)
+    if let Some(title) = get_alias_target(db, server_id, &name_lc).await? {
+        return Err(Box::new(CustomError::new(&format!("{name_lc} is an alias of {title}. Use /faq_edit alias remove to remove the alias."))))
+    }
+    let mut transaction = db.begin().await?;
+    let removed = sqlx::query!(r#"DELETE FROM faq WHERE server_id = $1 AND title = $2"#, server_id, name_lc)
+        .execute(&mut *transaction)
         .await?
-        .rows_affected() {
+        .rows_affected();
+    sqlx::query!(r#"DELETE FROM faq_aliases WHERE server_id = $1 AND title = $2"#, server_id, name_lc)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM faq_triggers WHERE server_id = $1 AND title = $2"#, server_id, name_lc)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM faq_namespace_entries
+        WHERE title = $2 AND namespace IN (SELECT name FROM faq_namespaces WHERE server_id = $1)"#, server_id, name_lc)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+    if let Err(e) = ctx.data().faq_suggestions.reload().await {
+        error!("Error while updating faq suggestion cache: {e}");
+    }
+    match removed {
         0 => {
             ctx.say(format!("FAQ entry {name_lc} does not exist in database")).await?;
         },
         _ => {
-            ctx.say(format!("FAQ entry {name_lc} removed from database")).await?;
+            ctx.say(format!("FAQ entry {name_lc} and its aliases removed from database")).await?;
         },
     };
     Ok(())
 }
 
-/// Link two faq titles to the same content
-#[allow(clippy::unused_async, clippy::cast_possible_wrap)]
-#[poise::command(prefix_command, slash_command, guild_only)]
-pub async fn link(
+/// Manage alternative names for FAQ entries
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, subcommands("alias_add", "alias_remove", "alias_list"), subcommand_required)]
+pub async fn alias(
+    _ctx: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Add an alternative name for an FAQ entry
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, rename = "add")]
+pub async fn alias_add(
     ctx: Context<'_>,
-    #[description = "Name for link"]
-    name: String,
+    #[description = "Existing FAQ entry"]
     #[autocomplete = "autocomplete_faq"]
-    #[description = "Existing FAQ entry to link to"]
-    link_to: String,
+    name: String,
+    #[description = "Alternative name for the entry"]
+    #[rest]
+    alias: String,
 ) -> Result<(), Error> {
     let name_lc = util::capitalize(&name.to_lowercase());
-    let link_to_lc = util::capitalize(&link_to.to_lowercase());
-    let Some(server) = ctx.guild_id() else {
-        return Err(Box::new(CustomError::new("Could not get server ID")))
-    };
-    let server_id = server.get() as i64;
+    let alias_lc = util::capitalize(&alias.trim().to_lowercase());
+    let server_id = util::get_server_id(ctx)?;
     let db = &ctx.data().database;
 
-    // Check if name already exists
-    if sqlx::query!(r#"SELECT title FROM faq WHERE server_id = $1 AND title = $2"#, server_id, name_lc)
-        .fetch_optional(db)
-        .await?
-        .is_some()
-    {
-        return Err(Box::new(CustomError::new(&format!("Error: An faq entry with title {name_lc} already exists"))));
+    // Aliases of aliases point at the entry itself, so there are never chains
+    let Some(entry) = find_faq_entry(db, server_id, &name_lc).await? else {
+        return Err(Box::new(CustomError::new(&format!("Error: Could not find FAQ entry {name_lc}"))))
     };
+    if let Some(existing) = find_faq_entry(db, server_id, &alias_lc).await? {
+        return Err(Box::new(CustomError::new(&format!("Error: {alias_lc} is already used by FAQ entry {}", existing.title))))
+    }
+    sqlx::query!(r#"INSERT INTO faq_aliases (server_id, alias, title) VALUES ($1, $2, $3)"#, server_id, alias_lc, entry.title)
+        .execute(db)
+        .await?;
+    ctx.say(format!("Added alias {alias_lc} for FAQ entry {}", entry.title)).await?;
+    Ok(())
+}
 
-    // Find entry to link to
-    let linked_entry_opt = sqlx::query_as!(FaqEntry, 
-        r#"SELECT title, contents, image, link FROM faq WHERE server_id = $1 AND title = $2"#, server_id, link_to_lc)
-        .fetch_optional(db)
+/// Remove an alternative name of an FAQ entry
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, rename = "remove")]
+pub async fn alias_remove(
+    ctx: Context<'_>,
+    #[description = "Alias to remove"]
+    #[autocomplete = "autocomplete_faq"]
+    #[rest]
+    alias: String,
+) -> Result<(), Error> {
+    let alias_lc = util::capitalize(&alias.trim().to_lowercase());
+    let server_id = util::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let Some(title) = get_alias_target(db, server_id, &alias_lc).await? else {
+        return Err(Box::new(CustomError::new(&format!("Error: {alias_lc} is not an alias"))))
+    };
+    sqlx::query!(r#"DELETE FROM faq_aliases WHERE server_id = $1 AND alias = $2"#, server_id, alias_lc)
+        .execute(db)
         .await?;
+    ctx.say(format!("Removed alias {alias_lc} of FAQ entry {title}")).await?;
+    Ok(())
+}
 
-    // Check if link target is link. Resolve chain if needed.
-    if let Some(linked_entry) = linked_entry_opt {
-        let link_no_chain = linked_entry.link.map_or(link_to_lc, |link| link);
-        let timestamp = ctx.created_at().timestamp();
-        let author_id = ctx.author().id.get() as i64;
-        sqlx::query!(r#"INSERT INTO faq (server_id, title, edit_time, author, link)
-            VALUES ($1, $2, $3, $4, $5)"#, server_id, name_lc, timestamp, author_id, link_no_chain)
-            .execute(db)
-            .await?;
-        ctx.say(format!("FAQ link {name_lc} added to database, linking to {link_no_chain}")).await?;
+/// List the aliases of an FAQ entry, or of all entries
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, rename = "list")]
+pub async fn alias_list(
+    ctx: Context<'_>,
+    #[description = "FAQ entry, all entries if left empty"]
+    #[autocomplete = "autocomplete_faq"]
+    #[rest]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let server_id = util::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let embed = if let Some(name) = name {
+        let name_lc = util::capitalize(&name.to_lowercase());
+        let entry = get_faq_entry(db, server_id, &name_lc).await?;
+        let aliases = sqlx::query!(r#"SELECT alias FROM faq_aliases WHERE server_id = $1 AND title = $2 ORDER BY alias"#, server_id, entry.title)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|a| a.alias)
+            .collect::<Vec<String>>();
+        let description = if aliases.is_empty() { "No aliases".to_owned() } else { aliases.join(", ") };
+        serenity::CreateEmbed::new()
+            .title(format!("Aliases of {}", entry.title))
+            .description(description)
     } else {
-        return Err(Box::new(CustomError::new(&format!("Error: Could not find FAQ entry {link_to_lc} to link to"))));
+        let aliases = sqlx::query!(r#"SELECT alias, title FROM faq_aliases WHERE server_id = $1 ORDER BY title, alias"#, server_id)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|a| format!("{} → {}", a.alias, a.title))
+            .collect::<Vec<String>>();
+        let description = if aliases.is_empty() { "No aliases".to_owned() } else { util::embed_list_field(&aliases) };
+        serenity::CreateEmbed::new()
+            .title("FAQ aliases")
+            .description(description)
+    };
+    ctx.send(CreateReply::default().embed(embed.colour(serenity::Colour::GOLD))).await?;
+    Ok(())
+}
+
+#[allow(clippy::unused_async)]
+async fn autocomplete_category(
+    ctx: Context<'_>,
+    partial: &str,
+) -> Vec<String> {
+    let Ok(server_id) = util::get_server_id(ctx) else {
+        return vec![]
+    };
+    let pattern = format!("{}%", partial.to_lowercase());
+    sqlx::query!(r#"SELECT DISTINCT category AS "category!" FROM faq
+        WHERE server_id = $1 AND category IS NOT NULL AND lower(category) LIKE $2 ORDER BY category LIMIT 25"#, server_id, pattern)
+        .fetch_all(&ctx.data().database)
+        .await
+        .map_or_else(|e| {
+            error!("Error while autocompleting faq category: {e}");
+            vec![]
+        }, |rows| rows.into_iter().map(|r| r.category).collect())
+}
+
+/// Set the category an FAQ entry is listed under
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn category(
+    ctx: Context<'_>,
+    #[description = "FAQ entry"]
+    #[autocomplete = "autocomplete_faq"]
+    name: String,
+    #[description = "Category, removes the category if left empty"]
+    #[autocomplete = "autocomplete_category"]
+    #[rest]
+    category: Option<String>,
+) -> Result<(), Error> {
+    let name_lc = util::capitalize(&name.to_lowercase());
+    let server_id = util::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let Some(entry) = find_faq_entry(db, server_id, &name_lc).await? else {
+        return Err(Box::new(CustomError::new(&format!("Could not find FAQ entry {name_lc}"))))
+    };
+    let category = category
+        .map(|c| util::capitalize(&c.trim().to_lowercase()))
+        .filter(|c| !c.is_empty());
+    if category.as_ref().is_some_and(|c| c.chars().count() > 100) {
+        return Err(Box::new(CustomError::new("Category names can be at most 100 characters long")))
+    }
+    sqlx::query!(r#"UPDATE faq SET category = $3 WHERE server_id = $1 AND title = $2"#, server_id, entry.title, category)
+        .execute(db)
+        .await?;
+    match category {
+        Some(c) => ctx.say(format!("FAQ entry {} is now listed under {c}", entry.title)).await?,
+        None => ctx.say(format!("FAQ entry {} no longer has a category", entry.title)).await?,
     };
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Show earlier versions of an FAQ entry and what changed in each
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn history(
+    ctx: Context<'_>,
+    #[description = "FAQ entry"]
+    #[autocomplete = "autocomplete_faq"]
+    #[rest]
+    name: String,
+) -> Result<(), Error> {
+    let name_lc = util::capitalize(&name.to_lowercase());
+    let server_id = util::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let name_lc = get_alias_target(db, server_id, &name_lc).await?.unwrap_or(name_lc);
+    let revisions = get_faq_revisions(db, server_id, &name_lc).await?;
+    let latest = revisions.len();
+    let pages = revisions.iter()
+        .enumerate()
+        .map(|(i, revision)| {
+            let previous = i.checked_sub(1).and_then(|p| revisions.get(p));
+            let latest_note = if i + 1 == latest { " (latest)" } else { "" };
+            serenity::CreateEmbed::new()
+                .title(format!("{name_lc}: revision {}{latest_note}", revision.revision))
+                .description(describe_revision(previous, revision))
+                .colour(serenity::Colour::GOLD)
+        })
+        .rev()
+        .collect::<Vec<serenity::CreateEmbed>>();
+    util::paginate_embeds(ctx, pages).await
+}
+
+/// Restore an earlier version of an FAQ entry
+#[allow(clippy::unused_async, clippy::cast_possible_wrap)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn revert(
+    ctx: Context<'_>,
+    #[description = "FAQ entry"]
+    #[autocomplete = "autocomplete_faq"]
+    name: String,
+    #[description = "Revision to restore, see /faq_edit history"]
+    revision: i64,
+) -> Result<(), Error> {
+    let name_lc = util::capitalize(&name.to_lowercase());
+    let server_id = util::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let name_lc = get_alias_target(db, server_id, &name_lc).await?.unwrap_or(name_lc);
+    let Some(old) = get_faq_revisions(db, server_id, &name_lc).await?
+        .into_iter()
+        .find(|r| r.revision == revision) else {
+        return Err(Box::new(CustomError::new(&format!("FAQ entry {name_lc} has no revision {revision}"))))
+    };
+    let timestamp = ctx.created_at().timestamp();
+    let author_id = ctx.author().id.get() as i64;
+    let content = FaqContent { contents: old.contents, image: old.image, body: old.body };
+    let new_revision = save_faq(db, server_id, &name_lc, &content, timestamp, author_id).await?;
+    let title = format!(r#"Restored revision {revision} of "{name_lc}" as revision {new_revision}"#);
+    ctx.send(render_faq(title, content)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn diffs_lines() {
+        let old = "Install the mod\nRestart the game\nDone";
+        let new = "Download the mod\nInstall the mod\nDone\nHave fun";
+        assert_eq!(line_diff(old, new), vec!["+ Download the mod", "- Restart the game", "+ Have fun"]);
+        assert_eq!(line_diff("", "a\nb"), vec!["+ a", "+ b"]);
+        assert!(line_diff(old, old).is_empty());
+    }
+
+    #[tokio::test]
+    async fn searches_titles_aliases_and_contents() {
+        let db = util::test_database().await;
+        let belts = FaqContent { contents: Some("Transport belts move items".to_owned()), ..Default::default() };
+        let trains = FaqContent { contents: Some("Trains need rails and belts to unload".to_owned()), ..Default::default() };
+        save_faq(&db, 1, "Belts", &belts, 0, 5).await.unwrap();
+        save_faq(&db, 1, "Trains", &trains, 0, 5).await.unwrap();
+        save_faq(&db, 2, "Belts", &belts, 0, 5).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_aliases (server_id, alias, title) VALUES (1, 'Conveyor', 'Belts')"#).execute(&db).await.unwrap();
+
+        let titles = |hits: Vec<FaqSearchHit>| hits.into_iter().map(|h| h.title).collect::<Vec<String>>();
+        assert_eq!(titles(search_faqs(&db, 1, "belt").await.unwrap()), vec!["Belts", "Trains"]);
+        assert_eq!(titles(search_faqs(&db, 1, "conveyor").await.unwrap()), vec!["Belts"]);
+        assert_eq!(titles(search_faqs(&db, 1, "rails, unload!").await.unwrap()), vec!["Trains"]);
+        assert!(search_faqs(&db, 1, "\"*").await.unwrap().is_empty());
+
+        rename_faq(&db, 1, "Trains", "Railways").await.unwrap();
+        assert_eq!(titles(search_faqs(&db, 1, "unload").await.unwrap()), vec!["Railways"]);
+    }
+
+    #[test]
+    fn groups_index_by_category() {
+        let entry = |title: &str, category: Option<&str>| (title.to_owned(), category.map(str::to_owned));
+        let entries = vec![
+            entry("Trains", Some("Logistics")),
+            entry("Mods", None),
+            entry("Belts", Some("Logistics")),
+            entry("Inserters", Some("Logistics")),
+            entry("Biters", Some("Combat")),
+        ];
+        let pages = index_pages(entries, 2);
+        let categories = pages.iter().map(|(c, _)| c.as_str()).collect::<Vec<&str>>();
+        assert_eq!(categories, vec!["Combat", "Logistics", "Logistics", "Uncategorized"]);
+        assert_eq!(pages[1].1, vec!["Belts", "Inserters"]);
+        assert_eq!(pages[2].1, vec!["Trains"]);
+    }
+}
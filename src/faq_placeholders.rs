@@ -0,0 +1,250 @@
+use std::{collections::{HashMap, HashSet}, sync::{LazyLock, RwLock}, time::{Duration, Instant}};
+use regex::{Captures, Regex};
+use log::error;
+
+use crate::{Data, Error, custom_errors::CustomError, api_runtime, fff_commands, mods::EMBED_DESCRIPTION_LIMIT, wiki_commands};
+
+// Placeholders resolved per entry, as most of them need a web request
+const MAX_PLACEHOLDERS: usize = 10;
+
+// Time the results of FFF and wiki lookups are reused for
+const WEB_CACHE_DURATION: Duration = Duration::from_mins(30);
+
+// Fields of a mod that can be shown with `{mod:<name>.<field>}`
+const MOD_FIELDS: [&str; 4] = ["version", "title", "author", "link"];
+
+/// Placeholder in the contents of an FAQ entry, written between braces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder<'a> {
+    /// `{latest_fff}`: link to the newest FFF
+    LatestFff,
+    /// `{mod:<name>}` or `{mod:<name>.<field>}`: link to a mod, or one of its fields
+    Mod { name: &'a str, field: Option<&'a str> },
+    /// `{api:<Class>}` or `{api:<Class>.<member>}`: link into the runtime API documentation
+    Api { class: &'a str, member: Option<&'a str> },
+    /// `{wiki:<page>}`: link to the best matching wiki page
+    Wiki(&'a str),
+}
+
+static PLACEHOLDER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{(latest_fff|(?:mod|api|wiki):[^{}\n]+)\}").unwrap());
+
+/// Recent values of placeholders that need a web request, by the text between their braces
+pub type PlaceholderCache = HashMap<String, CachedValue>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedValue {
+    time: Instant,
+    /// `None` if the placeholder refers to something that does not exist
+    value: Option<String>,
+}
+
+/// Parse the text between the braces of a placeholder
+pub fn parse_placeholder(inner: &str) -> Option<Placeholder<'_>> {
+    if inner == "latest_fff" {
+        return Some(Placeholder::LatestFff)
+    }
+    let (kind, argument) = inner.split_once(':')?;
+    let argument = argument.trim();
+    if argument.is_empty() {
+        return None
+    }
+    match kind {
+        "mod" => Some(match argument.rsplit_once('.') {
+            Some((name, field)) if MOD_FIELDS.contains(&field) => Placeholder::Mod { name, field: Some(field) },
+            _ => Placeholder::Mod { name: argument, field: None },
+        }),
+        "api" => Some(match argument.split_once("::").or_else(|| argument.split_once('.')) {
+            Some((class, member)) => Placeholder::Api { class, member: Some(member) },
+            None => Placeholder::Api { class: argument, member: None },
+        }),
+        "wiki" => Some(Placeholder::Wiki(argument)),
+        _ => None,
+    }
+}
+
+// Text to show for a placeholder, `None` if what it refers to does not exist
+async fn resolve(data: &Data, placeholder: Placeholder<'_>) -> Result<Option<String>, Error> {
+    match placeholder {
+        Placeholder::LatestFff => {
+            let (number, url) = fff_commands::get_latest_fff().await?;
+            Ok(Some(format!("[FFF #{number}]({url})")))
+        },
+        Placeholder::Mod { name, field } => {
+            let Some(m) = sqlx::query!(r#"SELECT title, owner, version FROM mods WHERE name = $1"#, name)
+                .fetch_optional(&data.database)
+                .await? else {
+                return Ok(None)
+            };
+            let title = m.title.unwrap_or_else(|| name.to_owned());
+            Ok(match field {
+                Some("version") => m.version,
+                Some("title") => Some(title),
+                Some("author") => Some(m.owner),
+                _ => Some(format!("[{title}](https://mods.factorio.com/mod/{})", name.replace(' ', "%20"))),
+            })
+        },
+        Placeholder::Api { class, member } => {
+            let link = match data.runtime_api_cache.read() {
+                Ok(api) => api_runtime::class_link(&api, class, member),
+                Err(e) => return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}")))),
+            };
+            Ok(link.map(|url| {
+                let text = member.map_or_else(|| class.to_owned(), |m| format!("{class}.{m}"));
+                format!("[{text}]({url})")
+            }))
+        },
+        Placeholder::Wiki(page) => {
+            Ok(wiki_commands::opensearch_mediawiki(page).await?
+                .first()
+                .map(|title| format!("[{title}](https://wiki.factorio.com/{})", title.replace(' ', "_"))))
+        },
+    }
+}
+
+// Placeholder looked up less than `WEB_CACHE_DURATION` ago, `None` if it needs to be looked up
+fn get_cached(cache: &RwLock<PlaceholderCache>, inner: &str, now: Instant) -> Result<Option<CachedValue>, Error> {
+    match cache.read() {
+        Ok(c) => Ok(c.get(inner)
+            .filter(|cached| now.duration_since(cached.time) < WEB_CACHE_DURATION)
+            .cloned()),
+        Err(e) => Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}")))),
+    }
+}
+
+fn set_cached(cache: &RwLock<PlaceholderCache>, inner: &str, value: Option<String>, now: Instant) -> Result<(), Error> {
+    match cache.write() {
+        Ok(mut c) => {
+            c.retain(|_, cached| now.duration_since(cached.time) < WEB_CACHE_DURATION);
+            c.insert(inner.to_owned(), CachedValue { time: now, value });
+            Ok(())
+        },
+        Err(e) => Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}")))),
+    }
+}
+
+// Resolve a placeholder, reusing recent results of web requests
+async fn resolve_cached(data: &Data, inner: &str, placeholder: Placeholder<'_>) -> Result<Option<String>, Error> {
+    if !matches!(placeholder, Placeholder::LatestFff | Placeholder::Wiki(_)) {
+        return resolve(data, placeholder).await
+    }
+    let now = Instant::now();
+    if let Some(cached) = get_cached(&data.placeholder_cache, inner, now)? {
+        return Ok(cached.value)
+    }
+    let value = resolve(data, placeholder).await?;
+    set_cached(&data.placeholder_cache, inner, value.clone(), now)?;
+    Ok(value)
+}
+
+/// Whether a text has placeholders to resolve before it is shown
+pub fn has_placeholders(text: &str) -> bool {
+    PLACEHOLDER_REGEX.is_match(text)
+}
+
+// Distinct placeholders of a text in the order they are written, up to the resolving limit
+fn collect_placeholders(text: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    PLACEHOLDER_REGEX.captures_iter(text)
+        .map(|caps| caps[1].to_owned())
+        .filter(|inner| seen.insert(inner.clone()))
+        .take(MAX_PLACEHOLDERS)
+        .collect()
+}
+
+/// Replace placeholders with their values, leaving placeholders without a value as written
+pub fn fill_placeholders(text: &str, values: &HashMap<String, String>) -> String {
+    PLACEHOLDER_REGEX
+        .replace_all(text, |caps: &Captures| values.get(&caps[1]).cloned().unwrap_or_else(|| caps[0].to_owned()))
+        .into_owned()
+}
+
+/// Resolve the placeholders in the contents of an FAQ entry.
+/// Placeholders that cannot be resolved are left as written, so they are easy to spot and fix.
+pub async fn resolve_placeholders(data: &Data, text: &str) -> String {
+    let placeholders = collect_placeholders(text);
+    let lookups = placeholders.iter().filter_map(|inner| {
+        let placeholder = parse_placeholder(inner)?;
+        Some(async move { (inner, resolve_cached(data, inner, placeholder).await) })
+    });
+    let mut values = HashMap::new();
+    for (inner, result) in futures::future::join_all(lookups).await {
+        match result {
+            Ok(Some(value)) => {
+                values.insert(inner.clone(), value);
+            },
+            Ok(None) => (),
+            Err(e) => error!("Error while resolving FAQ placeholder {inner}: {e}"),
+        }
+    }
+    trim_description(fill_placeholders(text, &values))
+}
+
+// Cut text to the embed description limit, as placeholders can make it longer than what was saved
+fn trim_description(mut text: String) -> String {
+    const MARKER: &str = "\n<Trimmed>";
+    if text.chars().count() > EMBED_DESCRIPTION_LIMIT {
+        if let Some((cut, _)) = text.char_indices().nth(EMBED_DESCRIPTION_LIMIT - MARKER.len()) {
+            text.truncate(cut);
+        }
+        text.push_str(MARKER);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parses_and_fills_placeholders() {
+        assert_eq!(parse_placeholder("latest_fff"), Some(Placeholder::LatestFff));
+        assert_eq!(parse_placeholder("mod:Krastorio2.version"), Some(Placeholder::Mod { name: "Krastorio2", field: Some("version") }));
+        assert_eq!(parse_placeholder("mod:flib"), Some(Placeholder::Mod { name: "flib", field: None }));
+        assert_eq!(parse_placeholder("mod:some.mod"), Some(Placeholder::Mod { name: "some.mod", field: None }));
+        assert_eq!(parse_placeholder("api:LuaEntity::get_inventory"), Some(Placeholder::Api { class: "LuaEntity", member: Some("get_inventory") }));
+        assert_eq!(parse_placeholder("api:LuaEntity"), Some(Placeholder::Api { class: "LuaEntity", member: None }));
+        assert_eq!(parse_placeholder("wiki: Belt transport system"), Some(Placeholder::Wiki("Belt transport system")));
+        assert_eq!(parse_placeholder("wiki:"), None);
+
+        let values = HashMap::from([("mod:flib.version".to_owned(), "0.14.0".to_owned())]);
+        assert_eq!(
+            fill_placeholders("flib is at {mod:flib.version}, {mod:nope.version} and {other} stay", &values),
+            "flib is at 0.14.0, {mod:nope.version} and {other} stay",
+        );
+    }
+
+    #[test]
+    fn collects_placeholders_in_order() {
+        let text = (0..12).map(|i| format!("{{mod:m{i}}} {{wiki:Belt}}")).collect::<Vec<String>>().join(" ");
+        let placeholders = collect_placeholders(&text);
+        assert_eq!(placeholders.len(), MAX_PLACEHOLDERS);
+        assert_eq!(placeholders[..3], ["mod:m0", "wiki:Belt", "mod:m1"]);
+        assert_eq!(placeholders.last().map(String::as_str), Some("mod:m8"));
+    }
+
+    #[test]
+    fn trims_filled_text() {
+        assert_eq!(trim_description("short".to_owned()), "short");
+        let trimmed = trim_description("ä".repeat(5000));
+        assert_eq!(trimmed.chars().count(), EMBED_DESCRIPTION_LIMIT);
+        assert!(trimmed.ends_with("ä\n<Trimmed>"));
+    }
+
+    #[test]
+    fn caches_web_values() {
+        let cache = RwLock::new(PlaceholderCache::new());
+        let now = Instant::now();
+        assert_eq!(get_cached(&cache, "latest_fff", now).unwrap(), None);
+        set_cached(&cache, "latest_fff", Some("FFF".to_owned()), now).unwrap();
+        set_cached(&cache, "wiki:Nothing", None, now).unwrap();
+        let value = |cached: Option<CachedValue>| cached.map(|c| c.value);
+        assert_eq!(value(get_cached(&cache, "latest_fff", now + Duration::from_mins(1)).unwrap()), Some(Some("FFF".to_owned())));
+        assert_eq!(value(get_cached(&cache, "wiki:Nothing", now).unwrap()), Some(None));
+        assert_eq!(get_cached(&cache, "latest_fff", now + WEB_CACHE_DURATION).unwrap(), None);
+
+        // Storing a value drops the expired ones
+        set_cached(&cache, "wiki:Belt", None, now + WEB_CACHE_DURATION).unwrap();
+        assert_eq!(cache.read().unwrap().len(), 1);
+    }
+}
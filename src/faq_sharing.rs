@@ -0,0 +1,328 @@
+use poise::serenity_prelude as serenity;
+use poise::CreateReply;
+use sqlx::{Pool, Sqlite};
+use log::error;
+
+use crate::{Context, Error, custom_errors::CustomError, faq_commands, util};
+
+const MAX_NAMESPACE_LENGTH: usize = 32;
+
+/// FAQ entry found in a namespace the server subscribes to
+#[derive(Debug, Clone)]
+pub struct SharedFaqEntry {
+    pub namespace: String,
+    pub title: String,
+    pub contents: Option<String>,
+    pub image: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Check a namespace name, which may contain lowercase letters, digits and dashes
+pub fn namespace_name(name: &str) -> Result<String, Error> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() || name.len() > MAX_NAMESPACE_LENGTH || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(Box::new(CustomError::new(&format!(
+            "Namespace names must be 1 to {MAX_NAMESPACE_LENGTH} characters long and only contain letters, digits and dashes"
+        ))))
+    }
+    Ok(name)
+}
+
+/// Entry with the title `name`, or the entry `name` is an alias of on the publishing server,
+/// from the namespaces `server_id` subscribes to.
+pub async fn find_shared_faq(db: &Pool<Sqlite>, server_id: i64, name: &str) -> Result<Option<SharedFaqEntry>, Error> {
+    Ok(sqlx::query_as!(SharedFaqEntry, r#"
+        SELECT n.name AS namespace, faq.title, faq.contents, faq.image, faq.body
+        FROM faq_namespace_subscriptions s
+        JOIN faq_namespaces n ON n.name = s.namespace
+        JOIN faq_namespace_entries e ON e.namespace = n.name
+        JOIN faq ON faq.server_id = n.server_id AND faq.title = e.title
+        WHERE s.server_id = $2
+            AND e.title = COALESCE((SELECT title FROM faq_aliases WHERE server_id = n.server_id AND alias = $1), $1)
+        ORDER BY n.name
+        LIMIT 1"#,
+        name, server_id
+    )
+        .fetch_optional(db)
+        .await?)
+}
+
+// Namespace `name` if it is owned by `server_id`
+async fn get_own_namespace(db: &Pool<Sqlite>, server_id: i64, name: &str) -> Result<String, Error> {
+    let namespace = namespace_name(name)?;
+    let owner = sqlx::query!(r#"SELECT server_id FROM faq_namespaces WHERE name = $1"#, namespace)
+        .fetch_optional(db)
+        .await?;
+    match owner {
+        Some(o) if o.server_id == server_id => Ok(namespace),
+        Some(_) => Err(Box::new(CustomError::new(&format!("Namespace {namespace} belongs to another server")))),
+        None => Err(Box::new(CustomError::new(&format!("Namespace {namespace} does not exist")))),
+    }
+}
+
+#[allow(clippy::unused_async)]
+async fn autocomplete_namespace(
+    ctx: Context<'_>,
+    partial: &str,
+) -> Vec<String> {
+    let pattern = format!("{}%", partial.to_lowercase());
+    sqlx::query!(r#"SELECT name FROM faq_namespaces WHERE name LIKE $1 ORDER BY name LIMIT 25"#, pattern)
+        .fetch_all(&ctx.data().database)
+        .await
+        .map_or_else(|e| {
+            error!("Error while autocompleting faq namespace: {e}");
+            vec![]
+        }, |rows| rows.into_iter().map(|r| r.name).collect())
+}
+
+/// Share FAQ entries with other servers
+#[allow(clippy::unused_async)]
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    subcommands("create", "delete", "publish", "unpublish", "subscribe", "unsubscribe", "list"),
+    subcommand_required
+)]
+pub async fn share(
+    _ctx: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Create a namespace to publish FAQ entries of this server to
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn create(
+    ctx: Context<'_>,
+    #[description = "Name of the namespace, made of letters, digits and dashes"]
+    name: String,
+) -> Result<(), Error> {
+    let namespace = namespace_name(&name)?;
+    let server_id = util::get_server_id(ctx)?;
+    let created = sqlx::query!(r#"INSERT INTO faq_namespaces (name, server_id) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING"#, namespace, server_id)
+        .execute(&ctx.data().database)
+        .await?
+        .rows_affected();
+    if created == 0 {
+        return Err(Box::new(CustomError::new(&format!("Namespace {namespace} already exists"))))
+    }
+    ctx.say(format!("Created namespace {namespace}. Add entries to it with /faq_edit share publish.")).await?;
+    Ok(())
+}
+
+/// Delete a namespace of this server, unsubscribing all servers from it
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn delete(
+    ctx: Context<'_>,
+    #[description = "Namespace to delete"]
+    #[autocomplete = "autocomplete_namespace"]
+    namespace: String,
+) -> Result<(), Error> {
+    let server_id = util::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let namespace = get_own_namespace(db, server_id, &namespace).await?;
+    let mut transaction = db.begin().await?;
+    sqlx::query!(r#"DELETE FROM faq_namespace_entries WHERE namespace = $1"#, namespace)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM faq_namespace_subscriptions WHERE namespace = $1"#, namespace)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(r#"DELETE FROM faq_namespaces WHERE name = $1"#, namespace)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+    ctx.say(format!("Deleted namespace {namespace}")).await?;
+    Ok(())
+}
+
+/// Publish an FAQ entry of this server to one of its namespaces
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn publish(
+    ctx: Context<'_>,
+    #[description = "Namespace to publish to"]
+    #[autocomplete = "autocomplete_namespace"]
+    namespace: String,
+    #[description = "FAQ entry to publish"]
+    #[autocomplete = "faq_commands::autocomplete_faq"]
+    #[rest]
+    name: String,
+) -> Result<(), Error> {
+    let server_id = util::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let namespace = get_own_namespace(db, server_id, &namespace).await?;
+    let name_lc = util::capitalize(&name.trim().to_lowercase());
+    let Some(entry) = sqlx::query!(r#"SELECT title FROM faq
+        WHERE server_id = $1 AND title = COALESCE((SELECT title FROM faq_aliases WHERE server_id = $1 AND alias = $2), $2)"#, server_id, name_lc)
+        .fetch_optional(db)
+        .await? else {
+        return Err(Box::new(CustomError::new(&format!("Could not find FAQ entry {name_lc}"))))
+    };
+    sqlx::query!(r#"INSERT OR IGNORE INTO faq_namespace_entries (namespace, title) VALUES ($1, $2)"#, namespace, entry.title)
+        .execute(db)
+        .await?;
+    ctx.say(format!("Published FAQ entry {} to {namespace}. Edits on this server are shown to subscribers right away.", entry.title)).await?;
+    Ok(())
+}
+
+/// Stop publishing an FAQ entry to a namespace
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn unpublish(
+    ctx: Context<'_>,
+    #[description = "Namespace the entry is published to"]
+    #[autocomplete = "autocomplete_namespace"]
+    namespace: String,
+    #[description = "FAQ entry to stop publishing"]
+    #[autocomplete = "faq_commands::autocomplete_faq"]
+    #[rest]
+    name: String,
+) -> Result<(), Error> {
+    let server_id = util::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let namespace = get_own_namespace(db, server_id, &namespace).await?;
+    let name_lc = util::capitalize(&name.trim().to_lowercase());
+    let title = faq_commands::get_alias_target(db, server_id, &name_lc).await?.unwrap_or(name_lc);
+    let removed = sqlx::query!(r#"DELETE FROM faq_namespace_entries WHERE namespace = $1 AND title = $2"#, namespace, title)
+        .execute(db)
+        .await?
+        .rows_affected();
+    if removed == 0 {
+        return Err(Box::new(CustomError::new(&format!("FAQ entry {title} is not published to {namespace}"))))
+    }
+    ctx.say(format!("FAQ entry {title} is no longer published to {namespace}")).await?;
+    Ok(())
+}
+
+/// Show FAQ entries of a namespace on this server when it has no entry of its own
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn subscribe(
+    ctx: Context<'_>,
+    #[description = "Namespace to subscribe to"]
+    #[autocomplete = "autocomplete_namespace"]
+    namespace: String,
+) -> Result<(), Error> {
+    let namespace = namespace_name(&namespace)?;
+    let server_id = util::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let Some(owner) = sqlx::query!(r#"SELECT server_id FROM faq_namespaces WHERE name = $1"#, namespace)
+        .fetch_optional(db)
+        .await? else {
+        return Err(Box::new(CustomError::new(&format!("Namespace {namespace} does not exist"))))
+    };
+    if owner.server_id == server_id {
+        return Err(Box::new(CustomError::new(&format!("Namespace {namespace} belongs to this server"))))
+    }
+    sqlx::query!(r#"INSERT OR IGNORE INTO faq_namespace_subscriptions (server_id, namespace) VALUES ($1, $2)"#, server_id, namespace)
+        .execute(db)
+        .await?;
+    ctx.say(format!("Subscribed to {namespace}. Its entries are used when this server has no matching FAQ entry.")).await?;
+    Ok(())
+}
+
+/// Stop showing FAQ entries of a namespace on this server
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn unsubscribe(
+    ctx: Context<'_>,
+    #[description = "Namespace to unsubscribe from"]
+    #[autocomplete = "autocomplete_namespace"]
+    namespace: String,
+) -> Result<(), Error> {
+    let namespace = namespace_name(&namespace)?;
+    let server_id = util::get_server_id(ctx)?;
+    let removed = sqlx::query!(r#"DELETE FROM faq_namespace_subscriptions WHERE server_id = $1 AND namespace = $2"#, server_id, namespace)
+        .execute(&ctx.data().database)
+        .await?
+        .rows_affected();
+    if removed == 0 {
+        return Err(Box::new(CustomError::new(&format!("This server is not subscribed to {namespace}"))))
+    }
+    ctx.say(format!("Unsubscribed from {namespace}")).await?;
+    Ok(())
+}
+
+/// List the entries of a namespace, or the namespaces this server owns and subscribes to
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn list(
+    ctx: Context<'_>,
+    #[description = "Namespace to list the entries of"]
+    #[autocomplete = "autocomplete_namespace"]
+    namespace: Option<String>,
+) -> Result<(), Error> {
+    let server_id = util::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let embed = if let Some(namespace) = namespace {
+        let namespace = namespace_name(&namespace)?;
+        let titles = sqlx::query!(r#"SELECT e.title FROM faq_namespace_entries e
+            JOIN faq_namespaces n ON n.name = e.namespace
+            JOIN faq ON faq.server_id = n.server_id AND faq.title = e.title
+            WHERE e.namespace = $1 ORDER BY e.title"#, namespace)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|e| e.title)
+            .collect::<Vec<String>>();
+        let description = if titles.is_empty() { "No published entries".to_owned() } else { titles.join(", ") };
+        serenity::CreateEmbed::new()
+            .title(format!("FAQ entries in {namespace}"))
+            .description(description)
+    } else {
+        let owned = sqlx::query!(r#"SELECT n.name,
+                (SELECT COUNT(*) FROM faq_namespace_entries e WHERE e.namespace = n.name) AS "entries!: i64",
+                (SELECT COUNT(*) FROM faq_namespace_subscriptions s WHERE s.namespace = n.name) AS "subscribers!: i64"
+            FROM faq_namespaces n WHERE n.server_id = $1 ORDER BY n.name"#, server_id)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|n| format!("{}: {} entries, {} subscribers", n.name, n.entries, n.subscribers))
+            .collect::<Vec<String>>();
+        let subscribed = sqlx::query!(r#"SELECT namespace FROM faq_namespace_subscriptions WHERE server_id = $1 ORDER BY namespace"#, server_id)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|s| s.namespace)
+            .collect::<Vec<String>>();
+        let or_none = |items: &[String]| if items.is_empty() { "None".to_owned() } else { util::embed_list_field(items) };
+        serenity::CreateEmbed::new()
+            .title("Shared FAQ namespaces")
+            .field("Namespaces of this server", or_none(&owned), false)
+            .field("Subscribed namespaces", or_none(&subscribed), false)
+    };
+    ctx.send(CreateReply::default().embed(embed.colour(serenity::Colour::GOLD))).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::faq_commands::FaqContent;
+
+    #[tokio::test]
+    async fn finds_entries_of_subscribed_namespaces() {
+        let db = util::test_database().await;
+        let belts = FaqContent { contents: Some("Transport belts move items".to_owned()), ..Default::default() };
+        faq_commands::save_faq(&db, 1, "Belts", &belts, 0, 5).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_aliases (server_id, alias, title) VALUES (1, 'Conveyor', 'Belts')"#).execute(&db).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_namespaces (name, server_id) VALUES ('factorio', 1)"#).execute(&db).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_namespace_entries (namespace, title) VALUES ('factorio', 'Belts')"#).execute(&db).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_namespace_subscriptions (server_id, namespace) VALUES (2, 'factorio')"#).execute(&db).await.unwrap();
+
+        let shared = find_shared_faq(&db, 2, "Conveyor").await.unwrap().unwrap();
+        assert_eq!((shared.namespace.as_str(), shared.title.as_str()), ("factorio", "Belts"));
+        assert!(find_shared_faq(&db, 3, "Belts").await.unwrap().is_none());
+
+        faq_commands::rename_faq(&db, 1, "Belts", "Transport belts").await.unwrap();
+        assert!(find_shared_faq(&db, 2, "Belts").await.unwrap().is_none());
+        assert_eq!(find_shared_faq(&db, 2, "Transport belts").await.unwrap().unwrap().title, "Transport belts");
+
+        assert!(namespace_name("Mod-Makers").is_ok());
+        assert!(namespace_name("mod makers").is_err());
+    }
+}
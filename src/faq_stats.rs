@@ -0,0 +1,197 @@
+use poise::serenity_prelude as serenity;
+use poise::CreateReply;
+use sqlx::{Pool, Sqlite};
+use log::error;
+
+use crate::{Context, Error, util};
+
+// Rows listed per field of /faq_edit stats
+const STATS_LIMIT: i64 = 10;
+
+/// Longest period `/faq_edit stats` looks back, older requests are pruned
+pub const MAX_STATS_DAYS: i64 = 365;
+
+/// How a `/faq` request was answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaqLookup {
+    /// Entry or alias with the requested name
+    Exact,
+    /// Near match offered as "did you mean"
+    Close,
+    /// Nothing found
+    Miss,
+}
+
+impl FaqLookup {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Exact => "exact",
+            Self::Close => "close",
+            Self::Miss => "miss",
+        }
+    }
+}
+
+/// Record a `/faq` request. Errors are only logged, so they never keep an entry from being shown.
+pub async fn log_faq_usage(
+    db: &Pool<Sqlite>,
+    server_id: i64,
+    query: &str,
+    found: Option<(&str, Option<&str>)>,
+    lookup: FaqLookup,
+    time: i64,
+) {
+    let (title, namespace) = found.map_or((None, None), |(t, n)| (Some(t), n));
+    let lookup = lookup.as_str();
+    let result = sqlx::query!(r#"INSERT INTO faq_usage (server_id, query, title, namespace, lookup, time)
+        VALUES ($1, $2, $3, $4, $5, $6)"#, server_id, query, title, namespace, lookup, time)
+        .execute(db)
+        .await;
+    if let Err(e) = result {
+        error!("Error while logging faq usage: {e}");
+    }
+}
+
+/// Delete requests older than `/faq_edit stats` can look back. Returns the number of rows deleted.
+pub async fn prune_faq_usage(db: &Pool<Sqlite>, now: i64) -> Result<u64, Error> {
+    let cutoff = now - MAX_STATS_DAYS * 24 * 60 * 60;
+    Ok(sqlx::query!(r#"DELETE FROM faq_usage WHERE time < $1"#, cutoff)
+        .execute(db)
+        .await?
+        .rows_affected())
+}
+
+#[derive(Debug, Default)]
+pub struct FaqStats {
+    /// Entries shown, with the namespace of shared entries and the number of requests
+    pub most_used: Vec<(String, Option<String>, i64)>,
+    /// Entries of the server that were not shown, leaving out entries created since
+    pub unused: Vec<String>,
+    /// Queries that found nothing
+    pub failed: Vec<(String, i64)>,
+    /// Queries answered with a "did you mean", and the entry offered
+    pub close: Vec<(String, String, i64)>,
+}
+
+/// Usage of the FAQ entries of `server_id` since `since`
+pub async fn get_faq_stats(db: &Pool<Sqlite>, server_id: i64, since: i64) -> Result<FaqStats, Error> {
+    let most_used = sqlx::query!(r#"SELECT title AS "title!", namespace, COUNT(*) AS "uses!: i64" FROM faq_usage
+        WHERE server_id = $1 AND time >= $2 AND title IS NOT NULL
+        GROUP BY title, namespace ORDER BY COUNT(*) DESC, title LIMIT $3"#, server_id, since, STATS_LIMIT)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|r| (r.title, r.namespace, r.uses))
+        .collect();
+    let unused = sqlx::query!(r#"SELECT title FROM faq
+        WHERE server_id = $1
+            AND COALESCE((SELECT MIN(edit_time) FROM faq_revisions r WHERE r.server_id = faq.server_id AND r.title = faq.title), edit_time) < $2
+            AND title NOT IN (SELECT title FROM faq_usage
+                WHERE server_id = $1 AND time >= $2 AND namespace IS NULL AND title IS NOT NULL)
+        ORDER BY title"#, server_id, since)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|r| r.title)
+        .collect();
+    let failed = sqlx::query!(r#"SELECT query, COUNT(*) AS "count!: i64" FROM faq_usage
+        WHERE server_id = $1 AND time >= $2 AND lookup = 'miss'
+        GROUP BY query ORDER BY COUNT(*) DESC, query LIMIT $3"#, server_id, since, STATS_LIMIT)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|r| (r.query, r.count))
+        .collect();
+    let close = sqlx::query!(r#"SELECT query, title AS "title!", COUNT(*) AS "count!: i64" FROM faq_usage
+        WHERE server_id = $1 AND time >= $2 AND lookup = 'close' AND title IS NOT NULL
+        GROUP BY query, title ORDER BY COUNT(*) DESC, query LIMIT $3"#, server_id, since, STATS_LIMIT)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|r| (r.query, r.title, r.count))
+        .collect();
+    Ok(FaqStats { most_used, unused, failed, close })
+}
+
+/// Show which FAQ entries are used, and what people looked for but did not find
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn stats(
+    ctx: Context<'_>,
+    #[description = "Number of days to look back, 30 if left empty"]
+    #[min = 1]
+    #[max = 365]
+    days: Option<u16>,
+) -> Result<(), Error> {
+    let days = days.unwrap_or(30);
+    let server_id = util::get_server_id(ctx)?;
+    let since = ctx.created_at().timestamp() - i64::from(days) * 24 * 60 * 60;
+    let stats = get_faq_stats(&ctx.data().database, server_id, since).await?;
+
+    let or_none = |items: &[String]| if items.is_empty() { "None".to_owned() } else { util::embed_list_field(items) };
+    let most_used = stats.most_used.iter()
+        .map(|(title, namespace, uses)| {
+            namespace.as_ref().map_or_else(|| format!("{title}: {uses}"), |n| format!("{title} ({n}): {uses}"))
+        })
+        .collect::<Vec<String>>();
+    let failed = stats.failed.iter()
+        .map(|(query, count)| format!("{query}: {count}"))
+        .collect::<Vec<String>>();
+    let close = stats.close.iter()
+        .map(|(query, title, count)| format!("{query} → {title}: {count}"))
+        .collect::<Vec<String>>();
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("FAQ usage in the last {days} days"))
+        .field("Most used entries", or_none(&most_used), false)
+        .field("Unused entries", or_none(&stats.unused), false)
+        .field("Most common failed searches", or_none(&failed), false)
+        .field("Most common near matches", or_none(&close), false)
+        .colour(serenity::Colour::GOLD);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::faq_commands::{self, FaqContent};
+
+    #[tokio::test]
+    async fn summarizes_usage() {
+        let db = util::test_database().await;
+        let content = FaqContent { contents: Some("text".to_owned()), ..Default::default() };
+        faq_commands::save_faq(&db, 1, "Belts", &content, 0, 5).await.unwrap();
+        faq_commands::save_faq(&db, 1, "Trains", &content, 0, 5).await.unwrap();
+        faq_commands::save_faq(&db, 1, "Robots", &content, 200, 5).await.unwrap();
+
+        log_faq_usage(&db, 1, "Belts", Some(("Belts", None)), FaqLookup::Exact, 50).await;
+        log_faq_usage(&db, 1, "Belts", Some(("Belts", None)), FaqLookup::Exact, 150).await;
+        log_faq_usage(&db, 1, "Belt", Some(("Belts", None)), FaqLookup::Close, 150).await;
+        log_faq_usage(&db, 1, "Mods", Some(("Mods", Some("factorio"))), FaqLookup::Exact, 150).await;
+        log_faq_usage(&db, 1, "Nukes", None, FaqLookup::Miss, 150).await;
+        log_faq_usage(&db, 1, "Nukes", None, FaqLookup::Miss, 160).await;
+        log_faq_usage(&db, 2, "Trains", Some(("Trains", None)), FaqLookup::Exact, 150).await;
+
+        let stats = get_faq_stats(&db, 1, 100).await.unwrap();
+        assert_eq!(stats.most_used, vec![
+            ("Belts".to_owned(), None, 2),
+            ("Mods".to_owned(), Some("factorio".to_owned()), 1),
+        ]);
+        assert_eq!(stats.unused, vec!["Trains"]);
+        assert_eq!(stats.failed, vec![("Nukes".to_owned(), 2)]);
+        assert_eq!(stats.close, vec![("Belt".to_owned(), "Belts".to_owned(), 1)]);
+    }
+
+    #[tokio::test]
+    async fn prunes_old_usage() {
+        let db = util::test_database().await;
+        let day = 24 * 60 * 60;
+        let now = 1000 * day;
+        log_faq_usage(&db, 1, "Belts", None, FaqLookup::Miss, now - MAX_STATS_DAYS * day - 1).await;
+        log_faq_usage(&db, 1, "Trains", None, FaqLookup::Miss, now - MAX_STATS_DAYS * day).await;
+        assert_eq!(prune_faq_usage(&db, now).await.unwrap(), 1);
+        let stats = get_faq_stats(&db, 1, 0).await.unwrap();
+        assert_eq!(stats.failed, vec![("Trains".to_owned(), 1)]);
+    }
+}
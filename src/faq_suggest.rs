@@ -0,0 +1,444 @@
+use std::{collections::{HashMap, HashSet}, sync::{Arc, Mutex, RwLock}, time::{Duration, Instant}};
+use poise::serenity_prelude::{
+    self as serenity, ButtonStyle, ChannelId, ComponentInteraction, CreateActionRow, CreateAllowedMentions, CreateButton,
+    CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditInteractionResponse,
+};
+use poise::CreateReply;
+use regex::{Regex, RegexBuilder};
+use sqlx::{Pool, Sqlite};
+use log::error;
+
+use crate::{Context, Data, Error, custom_errors::CustomError, faq_commands, faq_placeholders, util};
+
+pub const SUGGESTION_BUTTON_PREFIX: &str = "faq_suggestion:";
+
+// Time before the same user gets another suggestion on a server
+const SUGGESTION_COOLDOWN: Duration = Duration::from_mins(10);
+
+// Limits keeping triggers cheap to check on every message
+const MAX_PATTERN_LENGTH: usize = 200;
+const TRIGGER_SIZE_LIMIT: usize = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum TriggerKind {
+    #[name = "Keyword"]
+    Keyword,
+    #[name = "Regex"]
+    Regex,
+}
+
+impl TriggerKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::Regex => "regex",
+        }
+    }
+
+    fn from_db(kind: &str) -> Option<Self> {
+        match kind {
+            "keyword" => Some(Self::Keyword),
+            "regex" => Some(Self::Regex),
+            _ => None,
+        }
+    }
+}
+
+/// Compile a trigger pattern, ignoring case. Keywords only match whole words.
+pub fn compile_trigger(kind: TriggerKind, pattern: &str) -> Result<Regex, Error> {
+    if pattern.trim().is_empty() || pattern.chars().count() > MAX_PATTERN_LENGTH {
+        return Err(Box::new(CustomError::new(&format!("Trigger patterns must be 1 to {MAX_PATTERN_LENGTH} characters long"))))
+    }
+    let source = match kind {
+        TriggerKind::Keyword => format!(r"\b{}\b", regex::escape(pattern.trim())),
+        TriggerKind::Regex => pattern.to_owned(),
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(true)
+        .size_limit(TRIGGER_SIZE_LIMIT)
+        .build()
+        .map_err(|e| -> Error { Box::new(CustomError::new(&format!("Invalid trigger pattern: {e}"))) })
+}
+
+#[derive(Debug, Clone)]
+pub struct FaqTrigger {
+    pub title: String,
+    pub kind: String,
+    pub pattern: String,
+}
+
+#[derive(Debug, Default)]
+struct SuggestionCache {
+    /// Compiled triggers of each server with the entry they suggest
+    triggers: HashMap<i64, Vec<(String, Regex)>>,
+    channels: HashSet<i64>,
+}
+
+/// Owns the `faq_triggers` and `faq_suggestion_channels` tables together with their cache,
+/// and the cooldowns of users that recently got a suggestion.
+#[derive(Debug, Clone)]
+pub struct FaqSuggestions {
+    db: Pool<Sqlite>,
+    cache: Arc<RwLock<SuggestionCache>>,
+    cooldowns: Arc<Mutex<HashMap<(i64, u64), Instant>>>,
+}
+
+impl FaqSuggestions {
+    pub fn new(db: Pool<Sqlite>) -> Self {
+        Self { db, cache: Arc::new(RwLock::new(SuggestionCache::default())), cooldowns: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    /// Reload the cache from the database. Triggers that no longer compile are skipped.
+    pub async fn reload(&self) -> Result<(), Error> {
+        let mut triggers: HashMap<i64, Vec<(String, Regex)>> = HashMap::new();
+        for trigger in sqlx::query!(r#"SELECT server_id, title, kind, pattern FROM faq_triggers ORDER BY title"#)
+            .fetch_all(&self.db)
+            .await?
+        {
+            let Some(kind) = TriggerKind::from_db(&trigger.kind) else {
+                error!("Unknown kind of FAQ trigger {}: {}", trigger.pattern, trigger.kind);
+                continue;
+            };
+            match compile_trigger(kind, &trigger.pattern) {
+                Ok(regex) => triggers.entry(trigger.server_id).or_default().push((trigger.title, regex)),
+                Err(e) => error!("Error while compiling FAQ trigger {}: {e}", trigger.pattern),
+            }
+        }
+        let channels = sqlx::query!(r#"SELECT channel_id FROM faq_suggestion_channels"#)
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(|c| c.channel_id)
+            .collect();
+        match self.cache.write() {
+            Ok(mut c) => {
+                *c = SuggestionCache { triggers, channels };
+                Ok(())
+            },
+            Err(e) => Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}")))),
+        }
+    }
+
+    /// Add a trigger for an FAQ entry. Returns `false` if the entry already has this pattern.
+    pub async fn add_trigger(&self, server_id: i64, title: &str, kind: TriggerKind, pattern: &str) -> Result<bool, Error> {
+        compile_trigger(kind, pattern)?;
+        let kind = kind.as_str();
+        let added = sqlx::query!(r#"INSERT INTO faq_triggers (server_id, title, kind, pattern) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (server_id, title, pattern) DO NOTHING"#, server_id, title, kind, pattern)
+            .execute(&self.db)
+            .await?
+            .rows_affected() > 0;
+        self.reload().await?;
+        Ok(added)
+    }
+
+    /// Remove a trigger of an FAQ entry. Returns `false` if there was no such trigger.
+    pub async fn remove_trigger(&self, server_id: i64, title: &str, pattern: &str) -> Result<bool, Error> {
+        let removed = sqlx::query!(r#"DELETE FROM faq_triggers WHERE server_id = $1 AND title = $2 AND pattern = $3"#, server_id, title, pattern)
+            .execute(&self.db)
+            .await?
+            .rows_affected() > 0;
+        self.reload().await?;
+        Ok(removed)
+    }
+
+    /// Triggers of a server, or of a single entry
+    pub async fn get_triggers(&self, server_id: i64, title: Option<&str>) -> Result<Vec<FaqTrigger>, Error> {
+        Ok(sqlx::query_as!(FaqTrigger, r#"SELECT title, kind, pattern FROM faq_triggers
+            WHERE server_id = $1 AND ($2 IS NULL OR title = $2) ORDER BY title, pattern"#, server_id, title)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    /// Turn suggestions in a channel on or off. Returns `false` if nothing changed.
+    pub async fn set_channel(&self, server_id: i64, channel_id: i64, enabled: bool) -> Result<bool, Error> {
+        let changed = if enabled {
+            sqlx::query!(r#"INSERT OR IGNORE INTO faq_suggestion_channels (server_id, channel_id) VALUES ($1, $2)"#, server_id, channel_id)
+                .execute(&self.db)
+                .await?
+        } else {
+            sqlx::query!(r#"DELETE FROM faq_suggestion_channels WHERE server_id = $1 AND channel_id = $2"#, server_id, channel_id)
+                .execute(&self.db)
+                .await?
+        }.rows_affected() > 0;
+        self.reload().await?;
+        Ok(changed)
+    }
+
+    /// Channels of a server with suggestions turned on
+    pub async fn get_channels(&self, server_id: i64) -> Result<Vec<i64>, Error> {
+        Ok(sqlx::query!(r#"SELECT channel_id FROM faq_suggestion_channels WHERE server_id = $1"#, server_id)
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(|c| c.channel_id)
+            .collect())
+    }
+
+    /// FAQ entry to suggest for a message, if suggestions are on in one of its channels and the user did not get one recently.
+    /// The channels of a message in a thread are the thread and its parent channel.
+    /// Does not start the cooldown, call `start_cooldown` once the suggestion is sent.
+    pub fn suggest(&self, server_id: i64, channel_ids: &[i64], user_id: u64, text: &str, now: Instant) -> Result<Option<String>, Error> {
+        match self.cooldowns.lock() {
+            Ok(cooldowns) => {
+                if cooldowns.get(&(server_id, user_id)).is_some_and(|since| now.duration_since(*since) < SUGGESTION_COOLDOWN) {
+                    return Ok(None)
+                }
+            },
+            Err(e) => return Err(Box::new(CustomError::new(&format!("Error acquiring cooldowns: {e}")))),
+        }
+        match self.cache.read() {
+            Ok(c) => {
+                if !channel_ids.iter().any(|id| c.channels.contains(id)) {
+                    return Ok(None)
+                }
+                Ok(c.triggers.get(&server_id)
+                    .and_then(|triggers| triggers.iter().find(|(_, regex)| regex.is_match(text)))
+                    .map(|(title, _)| title.clone()))
+            },
+            Err(e) => Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}")))),
+        }
+    }
+
+    /// Start the cooldown of a user. Returns false if it was already running, e.g. from a concurrent message.
+    pub fn start_cooldown(&self, server_id: i64, user_id: u64, now: Instant) -> Result<bool, Error> {
+        match self.cooldowns.lock() {
+            Ok(mut cooldowns) => {
+                cooldowns.retain(|_, since| now.duration_since(*since) < SUGGESTION_COOLDOWN);
+                if cooldowns.contains_key(&(server_id, user_id)) {
+                    return Ok(false)
+                }
+                cooldowns.insert((server_id, user_id), now);
+                Ok(true)
+            },
+            Err(e) => Err(Box::new(CustomError::new(&format!("Error acquiring cooldowns: {e}")))),
+        }
+    }
+}
+
+/// Reply to a message matching a trigger with the FAQ entry it suggests.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn on_message(ctx: &serenity::Context, msg: &serenity::Message, data: &Data) -> Result<(), Error> {
+    let Some(guild_id) = msg.guild_id else {
+        return Ok(())
+    };
+    let server_id = guild_id.get() as i64;
+    // Messages in threads, including forum posts, also get suggestions if they are on in the parent channel
+    let parent_id = ctx.cache.guild(guild_id)
+        .and_then(|guild| guild.threads.iter().find(|t| t.id == msg.channel_id).and_then(|t| t.parent_id));
+    let channel_ids = std::iter::once(msg.channel_id).chain(parent_id).map(|c| c.get() as i64).collect::<Vec<i64>>();
+    let user_id = msg.author.id.get();
+    let Some(title) = data.faq_suggestions.suggest(server_id, &channel_ids, user_id, &msg.content, Instant::now())? else {
+        return Ok(())
+    };
+    // The cache may still have triggers of an entry that was just renamed or removed, which should not cost a cooldown
+    if faq_commands::find_faq_or_shared(&data.database, server_id, &title).await?.is_none() {
+        return Ok(())
+    }
+    if !data.faq_suggestions.start_cooldown(server_id, user_id, Instant::now())? {
+        return Ok(())
+    }
+    let embed = CreateEmbed::new()
+        .title(format!("This might help: {title}"))
+        .footer(CreateEmbedFooter::new("Suggested FAQ entry"))
+        .colour(serenity::Colour::GOLD);
+    let mut builder = CreateMessage::new()
+        .embed(embed)
+        .reference_message(msg)
+        .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+    let custom_id = format!("{SUGGESTION_BUTTON_PREFIX}{title}");
+    if custom_id.len() <= 100 {
+        let button = CreateButton::new(custom_id)
+            .label("Show")
+            .emoji('📖')
+            .style(ButtonStyle::Secondary);
+        builder = builder.components(vec![CreateActionRow::Buttons(vec![button])]);
+    }
+    msg.channel_id.send_message(ctx, builder).await?;
+    Ok(())
+}
+
+/// Expand a suggestion into the full FAQ entry.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn on_suggestion_button(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+) -> Result<(), Error> {
+    let (Some(title), Some(guild_id)) = (interaction.data.custom_id.strip_prefix(SUGGESTION_BUTTON_PREFIX), interaction.guild_id) else {
+        return Ok(())
+    };
+    let Some((title, mut content, _)) = faq_commands::find_faq_or_shared(&data.database, guild_id.get() as i64, title).await? else {
+        let message = CreateInteractionResponseMessage::new()
+            .content(format!("FAQ entry {title} no longer exists"))
+            .ephemeral(true);
+        interaction.create_response(ctx, CreateInteractionResponse::Message(message)).await?;
+        return Ok(())
+    };
+    // Placeholders may need web requests, so acknowledge the press before resolving them
+    interaction.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+    if let Some(contents) = &content.contents {
+        content.contents = Some(faq_placeholders::resolve_placeholders(data, contents).await);
+    }
+    // Drop the button, unless the entry has link buttons of its own
+    let edit = EditInteractionResponse::new().components(vec![]);
+    interaction.edit_response(ctx, faq_commands::render_faq(title, content).to_slash_initial_response_edit(edit)).await?;
+    Ok(())
+}
+
+/// Manage chat patterns that suggest FAQ entries
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, subcommands("trigger_add", "trigger_remove", "trigger_list"), subcommand_required)]
+pub async fn trigger(
+    _ctx: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Suggest an FAQ entry when a message contains a keyword or matches a regex
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, rename = "add")]
+pub async fn trigger_add(
+    ctx: Context<'_>,
+    #[description = "FAQ entry to suggest"]
+    #[autocomplete = "faq_commands::autocomplete_faq"]
+    name: String,
+    #[description = "Whether the pattern is a keyword or a regex"]
+    kind: TriggerKind,
+    #[description = "Keyword or regex, case is ignored"]
+    #[rest]
+    pattern: String,
+) -> Result<(), Error> {
+    let name_lc = util::capitalize(&name.trim().to_lowercase());
+    let server_id = util::get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let title = faq_commands::get_alias_target(db, server_id, &name_lc).await?.unwrap_or(name_lc);
+    if sqlx::query!(r#"SELECT title FROM faq WHERE server_id = $1 AND title = $2"#, server_id, title)
+        .fetch_optional(db)
+        .await?
+        .is_none()
+    {
+        return Err(Box::new(CustomError::new(&format!("Could not find FAQ entry {title}"))))
+    }
+    let pattern = pattern.trim();
+    if !ctx.data().faq_suggestions.add_trigger(server_id, &title, kind, pattern).await? {
+        return Err(Box::new(CustomError::new(&format!("FAQ entry {title} already has the trigger {pattern}"))))
+    }
+    ctx.say(format!("FAQ entry {title} will be suggested for messages matching {} `{pattern}`", kind.as_str())).await?;
+    Ok(())
+}
+
+/// Stop suggesting an FAQ entry for a pattern
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, rename = "remove")]
+pub async fn trigger_remove(
+    ctx: Context<'_>,
+    #[description = "FAQ entry the trigger belongs to"]
+    #[autocomplete = "faq_commands::autocomplete_faq"]
+    name: String,
+    #[description = "Keyword or regex to remove"]
+    #[rest]
+    pattern: String,
+) -> Result<(), Error> {
+    let name_lc = util::capitalize(&name.trim().to_lowercase());
+    let server_id = util::get_server_id(ctx)?;
+    let title = faq_commands::get_alias_target(&ctx.data().database, server_id, &name_lc).await?.unwrap_or(name_lc);
+    let pattern = pattern.trim();
+    if !ctx.data().faq_suggestions.remove_trigger(server_id, &title, pattern).await? {
+        return Err(Box::new(CustomError::new(&format!("FAQ entry {title} has no trigger {pattern}"))))
+    }
+    ctx.say(format!("Removed trigger {pattern} of FAQ entry {title}")).await?;
+    Ok(())
+}
+
+/// List the triggers of an FAQ entry, or of all entries
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, rename = "list")]
+pub async fn trigger_list(
+    ctx: Context<'_>,
+    #[description = "FAQ entry, all entries if left empty"]
+    #[autocomplete = "faq_commands::autocomplete_faq"]
+    #[rest]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let server_id = util::get_server_id(ctx)?;
+    let title = name.map(|n| util::capitalize(&n.trim().to_lowercase()));
+    let triggers = ctx.data().faq_suggestions.get_triggers(server_id, title.as_deref()).await?
+        .into_iter()
+        .map(|t| format!("{} ({}): `{}`", t.title, t.kind, t.pattern))
+        .collect::<Vec<String>>();
+    let channels = ctx.data().faq_suggestions.get_channels(server_id).await?
+        .into_iter()
+        .map(|c| format!("<#{c}>"))
+        .collect::<Vec<String>>();
+    let or_none = |items: &[String]| if items.is_empty() { "None".to_owned() } else { util::embed_list_field(items) };
+    let embed = CreateEmbed::new()
+        .title(title.map_or_else(|| "FAQ triggers".to_owned(), |t| format!("Triggers of {t}")))
+        .field("Triggers", or_none(&triggers), false)
+        .field("Channels with suggestions", or_none(&channels), false)
+        .colour(serenity::Colour::GOLD);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Turn FAQ suggestions in a channel on or off
+#[allow(clippy::unused_async, clippy::cast_possible_wrap)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn suggestions(
+    ctx: Context<'_>,
+    #[description = "Suggest FAQ entries in the channel"]
+    enabled: bool,
+    #[description = "Channel, the current channel if left empty"]
+    #[channel_types("Text", "News", "PublicThread", "PrivateThread", "Forum")]
+    channel: Option<ChannelId>,
+) -> Result<(), Error> {
+    let server_id = util::get_server_id(ctx)?;
+    let channel = channel.unwrap_or_else(|| ctx.channel_id());
+    let changed = ctx.data().faq_suggestions.set_channel(server_id, channel.get() as i64, enabled).await?;
+    let response = match (enabled, changed) {
+        (true, true) => format!("FAQ entries will be suggested in <#{channel}>"),
+        (true, false) => format!("FAQ entries are already suggested in <#{channel}>"),
+        (false, true) => format!("FAQ entries will no longer be suggested in <#{channel}>"),
+        (false, false) => format!("FAQ entries are not suggested in <#{channel}>"),
+    };
+    ctx.say(response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn compiles_triggers() {
+        let keyword = compile_trigger(TriggerKind::Keyword, "belt").unwrap();
+        assert!(keyword.is_match("My Belt is stuck"));
+        assert!(!keyword.is_match("The belts are stuck"));
+        let escaped = compile_trigger(TriggerKind::Keyword, "what.ever").unwrap();
+        assert!(!escaped.is_match("whatsever"));
+        assert!(compile_trigger(TriggerKind::Regex, r"belts?\s+(stuck|jammed)").unwrap().is_match("BELTS JAMMED"));
+        assert!(compile_trigger(TriggerKind::Regex, "(unclosed").is_err());
+        assert!(compile_trigger(TriggerKind::Keyword, " ").is_err());
+    }
+
+    #[tokio::test]
+    async fn suggests_once_per_cooldown() {
+        let db = util::test_database().await;
+        let suggestions = FaqSuggestions::new(db);
+        suggestions.add_trigger(1, "Belts", TriggerKind::Keyword, "belt").await.unwrap();
+        suggestions.set_channel(1, 10, true).await.unwrap();
+
+        let now = Instant::now();
+        assert_eq!(suggestions.suggest(1, &[10], 5, "my belt is stuck", now).unwrap(), Some("Belts".to_owned()));
+        // Matching alone does not start the cooldown
+        assert_eq!(suggestions.suggest(1, &[10], 5, "my belt is stuck", now).unwrap(), Some("Belts".to_owned()));
+        assert!(suggestions.start_cooldown(1, 5, now).unwrap());
+        assert!(!suggestions.start_cooldown(1, 5, now).unwrap());
+        assert_eq!(suggestions.suggest(1, &[10], 5, "another belt", now + Duration::from_mins(1)).unwrap(), None);
+        assert_eq!(suggestions.suggest(1, &[10], 6, "no match", now).unwrap(), None);
+        assert_eq!(suggestions.suggest(1, &[11], 6, "belt in other channel", now).unwrap(), None);
+        assert_eq!(suggestions.suggest(1, &[12, 10], 6, "belt in a thread", now).unwrap(), Some("Belts".to_owned()));
+        assert_eq!(suggestions.suggest(1, &[10], 5, "belt again", now + SUGGESTION_COOLDOWN).unwrap(), Some("Belts".to_owned()));
+    }
+}
@@ -0,0 +1,337 @@
+use std::{convert::Infallible, fmt::Write, net::SocketAddr};
+use hyper::{Body, Method, Request, Response, Server, StatusCode, header};
+use hyper::service::{make_service_fn, service_fn};
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use rand::{distributions::Alphanumeric, Rng};
+use sqlx::{Pool, Sqlite};
+
+use crate::{Context, Error, util::{get_server_id, is_mod}};
+
+// Maximum number of updates listed in a single feed
+const FEED_LENGTH: i64 = 50;
+
+struct FeedEntry {
+    name: String,
+    title: String,
+    author: String,
+    version: String,
+    state: String,
+    changelog: String,
+    released_at: i64,
+}
+
+impl FeedEntry {
+    fn heading(&self) -> String {
+        match self.state.as_str() {
+            "new" => format!("New mod: {} {}", self.title, self.version),
+            _ => format!("Updated mod: {} {}", self.title, self.version),
+        }
+    }
+
+    fn url(&self) -> String {
+        format!("https://mods.factorio.com/mod/{}", self.name).replace(' ', "%20")
+    }
+
+    fn id(&self) -> String {
+        format!("{}/changelog#{}", self.url(), self.version)
+    }
+
+    fn date(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.released_at, 0).unwrap_or_default()
+    }
+}
+
+enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+/// Serve feeds of mod updates on `address` until the process exits.
+pub async fn run(address: SocketAddr, db: Pool<Sqlite>) -> Result<(), Error> {
+    let make_service = make_service_fn(move |_| {
+        let db = db.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| handle_request(request, db.clone())))
+        }
+    });
+    info!("Serving mod update feeds on {address}");
+    Server::try_bind(&address)?.serve(make_service).await?;
+    Ok(())
+}
+
+// Routes: /feeds/<server id>/<token>.atom and /feeds/<server id>/<token>.rss
+async fn handle_request(request: Request<Body>, db: Pool<Sqlite>) -> Result<Response<Body>, Infallible> {
+    if request.method() != Method::GET {
+        return Ok(plain_response(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed"));
+    }
+    let Some((server_id, file)) = request.uri().path().strip_prefix("/feeds/").and_then(|path| path.split_once('/')) else {
+        return Ok(plain_response(StatusCode::NOT_FOUND, "Not found"));
+    };
+    let (token, format) = if let Some(token) = file.strip_suffix(".atom") {
+        (token, FeedFormat::Atom)
+    } else if let Some(token) = file.strip_suffix(".rss") {
+        (token, FeedFormat::Rss)
+    } else {
+        return Ok(plain_response(StatusCode::NOT_FOUND, "Not found"));
+    };
+    let Ok(server_id) = server_id.parse::<i64>() else {
+        return Ok(plain_response(StatusCode::NOT_FOUND, "Not found"));
+    };
+
+    match get_feed_entries(&db, server_id, token).await {
+        Ok(Some(entries)) => {
+            let (content_type, body) = match format {
+                FeedFormat::Atom => ("application/atom+xml; charset=utf-8", make_atom_feed(server_id, &entries)),
+                FeedFormat::Rss => ("application/rss+xml; charset=utf-8", make_rss_feed(server_id, &entries)),
+            };
+            Ok(Response::builder()
+                .header(header::CONTENT_TYPE, content_type)
+                .body(Body::from(body))
+                .unwrap_or_default())
+        },
+        Ok(None) => Ok(plain_response(StatusCode::NOT_FOUND, "Unknown feed")),
+        Err(e) => {
+            error!("Error building mod update feed: {e}");
+            Ok(plain_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"))
+        },
+    }
+}
+
+fn plain_response(status: StatusCode, text: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Body::from(text))
+        .unwrap_or_default()
+}
+
+/// Latest logged updates matching the server's subscriptions, or `None` if the server or token is not known.
+async fn get_feed_entries(db: &Pool<Sqlite>, server_id: i64, token: &str) -> Result<Option<Vec<FeedEntry>>, Error> {
+    if sqlx::query!(r#"SELECT server_id FROM servers WHERE server_id = $1 AND feed_token = $2"#, server_id, token)
+        .fetch_optional(db)
+        .await?
+        .is_none() {
+        return Ok(None);
+    }
+    let entries = sqlx::query_as!(FeedEntry, r#"
+        SELECT name, title, author, version, state, changelog, released_at FROM mod_update_log
+        WHERE (NOT EXISTS (SELECT 1 FROM subscribed_mods WHERE server_id = $1)
+                AND NOT EXISTS (SELECT 1 FROM subscribed_authors WHERE server_id = $1))
+            OR name IN (SELECT mod_name FROM subscribed_mods WHERE server_id = $1)
+            OR author IN (SELECT author_name FROM subscribed_authors WHERE server_id = $1)
+        ORDER BY released_at DESC
+        LIMIT $2"#, server_id, FEED_LENGTH)
+        .fetch_all(db)
+        .await?;
+    Ok(Some(entries))
+}
+
+/// Token of the server's feed URLs, created on first use
+async fn get_feed_token(db: &Pool<Sqlite>, server_id: i64, regenerate: bool) -> Result<String, Error> {
+    let existing = sqlx::query!(r#"SELECT feed_token FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await?
+        .and_then(|s| s.feed_token);
+    if let Some(token) = existing.filter(|_| !regenerate) {
+        return Ok(token)
+    }
+    let token = rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect::<String>();
+    sqlx::query!(r#"INSERT INTO servers (server_id, feed_token) VALUES ($1, $2)
+        ON CONFLICT (server_id) DO UPDATE SET feed_token = excluded.feed_token"#, server_id, token)
+        .execute(db)
+        .await?;
+    Ok(token)
+}
+
+/// Show the Atom and RSS feed links of this server's mod updates
+// Slash command only, as the links contain the feed token and prefix commands can not reply ephemerally
+#[poise::command(slash_command, guild_only, check="is_mod", ephemeral, category="Settings")]
+pub async fn feed_url(
+    ctx: Context<'_>,
+    #[description = "Replace the links, so the old ones stop working"]
+    regenerate: Option<bool>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let token = get_feed_token(&ctx.data().database, server_id, regenerate.unwrap_or(false)).await?;
+    let base_url = std::env::var("FEED_PUBLIC_URL")
+        .or_else(|_| std::env::var("FEED_SERVER_ADDRESS").map(|address| format!("http://{address}")))
+        .unwrap_or_else(|_| "http://127.0.0.1:8080".to_owned());
+    let base_url = base_url.trim_end_matches('/');
+    ctx.say(format!(
+        "Atom: <{base_url}/feeds/{server_id}/{token}.atom>\nRSS: <{base_url}/feeds/{server_id}/{token}.rss>\n\
+        Anyone with these links can read the feed. Use `regenerate` to replace them."
+    )).await?;
+    Ok(())
+}
+
+fn escape_xml(s: &str) -> String {
+    s.chars().fold(String::with_capacity(s.len()), |mut out, c| {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+        out
+    })
+}
+
+fn make_atom_feed(server_id: i64, entries: &[FeedEntry]) -> String {
+    let updated = entries.first().map_or_else(Utc::now, FeedEntry::date);
+    let mut feed = format!(r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+<title>Factorio mod updates</title>
+<id>urn:rhobot:feed:{server_id}</id>
+<link href="https://mods.factorio.com"/>
+<updated>{}</updated>
+"#, updated.to_rfc3339());
+    for entry in entries {
+        let _ = write!(feed, r#"<entry>
+<title>{}</title>
+<id>{}</id>
+<link href="{}"/>
+<updated>{}</updated>
+<author><name>{}</name></author>
+<content type="text">{}</content>
+</entry>
+"#,
+            escape_xml(&entry.heading()),
+            escape_xml(&entry.id()),
+            escape_xml(&entry.url()),
+            entry.date().to_rfc3339(),
+            escape_xml(&entry.author),
+            escape_xml(&entry.changelog),
+        );
+    }
+    feed.push_str("</feed>\n");
+    feed
+}
+
+fn make_rss_feed(server_id: i64, entries: &[FeedEntry]) -> String {
+    let mut feed = format!(r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0">
+<channel>
+<title>Factorio mod updates</title>
+<link>https://mods.factorio.com</link>
+<description>Mod updates followed by Discord server {server_id}</description>
+"#);
+    for entry in entries {
+        let _ = write!(feed, r#"<item>
+<title>{}</title>
+<guid isPermaLink="false">{}</guid>
+<link>{}</link>
+<pubDate>{}</pubDate>
+<description>{}</description>
+</item>
+"#,
+            escape_xml(&entry.heading()),
+            escape_xml(&entry.id()),
+            escape_xml(&entry.url()),
+            entry.date().to_rfc2822(),
+            escape_xml(&entry.changelog),
+        );
+    }
+    feed.push_str("</channel>\n</rss>\n");
+    feed
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::util::test_database;
+
+    fn entry(name: &str, title: &str, state: &str, changelog: &str) -> FeedEntry {
+        FeedEntry {
+            name: name.to_owned(),
+            title: title.to_owned(),
+            author: "raiguard".to_owned(),
+            version: "1.2.3".to_owned(),
+            state: state.to_owned(),
+            changelog: changelog.to_owned(),
+            released_at: 1_700_000_000,
+        }
+    }
+
+    async fn get(db: &Pool<Sqlite>, method: Method, path: &str) -> (StatusCode, String) {
+        let request = Request::builder().method(method).uri(path).body(Body::empty()).unwrap();
+        let response = handle_request(request, db.clone()).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn escapes_xml() {
+        assert_eq!(escape_xml(r#"Belts & <Trains> "fast" 'n' more"#), "Belts &amp; &lt;Trains&gt; &quot;fast&quot; &apos;n&apos; more");
+        assert_eq!(escape_xml("plain"), "plain");
+    }
+
+    #[test]
+    fn builds_escaped_feeds() {
+        let entries = vec![
+            entry("Rail & <Road>", r#"Rails "&" Roads"#, "updated", "- Fixed <script> & \"quotes\""),
+            entry("flib", "Factorio Library", "new", "Initial release"),
+        ];
+        let atom = make_atom_feed(5, &entries);
+        assert!(atom.contains("<id>urn:rhobot:feed:5</id>"));
+        assert!(atom.contains("<title>Updated mod: Rails &quot;&amp;&quot; Roads 1.2.3</title>"));
+        assert!(atom.contains("<title>New mod: Factorio Library 1.2.3</title>"));
+        assert!(atom.contains(r#"<link href="https://mods.factorio.com/mod/Rail%20&amp;%20&lt;Road&gt;"/>"#));
+        assert!(atom.contains(r#"<content type="text">- Fixed &lt;script&gt; &amp; &quot;quotes&quot;</content>"#));
+        assert!(atom.contains("<updated>2023-11-14T22:13:20+00:00</updated>"));
+        assert!(atom.ends_with("</feed>\n"));
+        assert_eq!(atom.matches("<entry>").count(), 2);
+
+        let rss = make_rss_feed(5, &entries);
+        assert!(rss.contains("<description>Mod updates followed by Discord server 5</description>"));
+        assert!(rss.contains("<title>Updated mod: Rails &quot;&amp;&quot; Roads 1.2.3</title>"));
+        assert!(rss.contains("<guid isPermaLink=\"false\">https://mods.factorio.com/mod/flib/changelog#1.2.3</guid>"));
+        assert!(rss.contains("<description>- Fixed &lt;script&gt; &amp; &quot;quotes&quot;</description>"));
+        assert!(rss.contains("<pubDate>Tue, 14 Nov 2023 22:13:20 +0000</pubDate>"));
+        assert!(rss.ends_with("</channel>\n</rss>\n"));
+        assert_eq!(rss.matches("<item>").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn routes_requests() {
+        let db = test_database().await;
+        sqlx::query!(r#"INSERT INTO servers (server_id) VALUES (3)"#).execute(&db).await.unwrap();
+        let token_1 = get_feed_token(&db, 1, false).await.unwrap();
+        let token_2 = get_feed_token(&db, 2, false).await.unwrap();
+        assert_eq!(get_feed_token(&db, 1, false).await.unwrap(), token_1);
+        sqlx::query!(r#"INSERT INTO subscribed_mods (server_id, mod_name) VALUES (2, 'flib')"#).execute(&db).await.unwrap();
+        sqlx::query!(r#"INSERT INTO mod_update_log (name, title, author, version, state, changelog, released_at)
+            VALUES ('flib', 'Factorio Library', 'raiguard', '0.14.0', 'updated', 'Fixes', 20),
+                ('Krastorio2', 'Krastorio 2', 'raiguard', '1.3.0', 'updated', 'More fixes', 10)"#)
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let (status, body) = get(&db, Method::GET, &format!("/feeds/1/{token_1}.atom")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.matches("<entry>").count(), 2);
+        let (status, body) = get(&db, Method::GET, &format!("/feeds/2/{token_2}.rss")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("Factorio Library"));
+        assert!(!body.contains("Krastorio 2"));
+
+        let unknown = (StatusCode::NOT_FOUND, "Unknown feed".to_owned());
+        assert_eq!(get(&db, Method::GET, &format!("/feeds/2/{token_1}.atom")).await, unknown);
+        assert_eq!(get(&db, Method::GET, "/feeds/3/.atom").await, unknown);
+        assert_eq!(get(&db, Method::GET, &format!("/feeds/4/{token_1}.atom")).await, unknown);
+        assert_eq!(get(&db, Method::GET, "/feeds/1.atom").await.0, StatusCode::NOT_FOUND);
+        assert_eq!(get(&db, Method::GET, &format!("/feeds/1/{token_1}.json")).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(get(&db, Method::GET, &format!("/feeds/abc/{token_1}.rss")).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(get(&db, Method::GET, &format!("/other/1/{token_1}.rss")).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(get(&db, Method::POST, &format!("/feeds/1/{token_1}.rss")).await.0, StatusCode::METHOD_NOT_ALLOWED);
+
+        let new_token = get_feed_token(&db, 1, true).await.unwrap();
+        assert_ne!(new_token, token_1);
+        assert_eq!(get(&db, Method::GET, &format!("/feeds/1/{token_1}.atom")).await, unknown);
+        assert_eq!(get(&db, Method::GET, &format!("/feeds/1/{new_token}.atom")).await.0, StatusCode::OK);
+    }
+}
@@ -71,6 +71,23 @@ async fn get_fff_data(number: i32) -> Result<FFFData, Error> {
     Ok(fff)
 }
 
+/// Number and link of the newest FFF on the blog
+pub async fn get_latest_fff() -> Result<(i32, String), Error> {
+    let response = reqwest::get("https://www.factorio.com/blog/").await?;
+    if response.status() != reqwest::StatusCode::OK {
+        return Err(Box::new(CustomError::new(&format!("Received HTTP status code {} while accessing FFF website", response.status().as_str()))))
+    }
+    let text = response.text().await?;
+    let document = Html::parse_document(&text);
+    let Ok(link_selector) = Selector::parse(r#"a[href*="/blog/post/fff-"]"#)
+        else {return Err(Box::new(CustomError::new("Failed to read FFF blog: could not find posts")))};
+    let Some(number) = document.select(&link_selector)
+        .filter_map(|link| link.value().attr("href")?.rsplit("fff-").next()?.trim_end_matches('/').parse::<i32>().ok())
+        .max()
+        else {return Err(Box::new(CustomError::new("Failed to read FFF blog: no FFF posts found")))};
+    Ok((number, format!("https://www.factorio.com/blog/post/fff-{number}")))
+}
+
 /// Link an FFF
 #[poise::command(prefix_command, slash_command, track_edits)]
 pub async fn fff(
@@ -4,17 +4,33 @@ mod mod_commands;
 mod mods;
 mod mod_search_api;
 mod faq_commands;
+mod faq_body;
+mod faq_placeholders;
+mod faq_sharing;
+mod faq_stats;
+mod faq_suggest;
 mod fff_commands;
 mod fun_commands;
 mod api_runtime;
 mod api_data;
 mod wiki_commands;
 mod custom_errors;
+mod outgoing_webhooks;
+mod notification_style;
+mod mod_notify;
+mod mod_list;
+mod modpack;
+mod subscriptions;
+mod server_commands;
+#[cfg(feature = "feed-server")]
+mod feed_server;
 mod util;
 
 use clokwerk::{AsyncScheduler, Job};
 use fff_commands::update_fff_channel_description;
-use mods::{get_mod_count, update_database, update_mod_cache, update_sub_cache, update_author_cache, ModCacheEntry, SubCacheEntry};
+use mods::{get_mod_count, update_database, update_mod_cache, update_author_cache, ModCacheEntry};
+use subscriptions::Subscriptions;
+use faq_suggest::FaqSuggestions;
 use mod_search_api::ModPortalCredentials;
 use faq_commands::{update_faq_cache, FaqCacheEntry};
 use tokio::time;
@@ -35,15 +51,20 @@ type Context<'a> = poise::Context<'a, Data, Error>;
 // Command separator for adding comments
 const SEPARATOR: char = '|';
 
+// Prefix of text commands
+const PREFIX: &str = "+";
+
 // Custom user data passed to all command functions
 pub struct Data {
     database: sqlx::SqlitePool,
     mod_cache: Arc<RwLock<Vec<ModCacheEntry>>>,
     faq_cache: Arc<RwLock<Vec<FaqCacheEntry>>>,
-    mod_subscription_cache: Arc<RwLock<Vec<SubCacheEntry>>>,
+    faq_suggestions: FaqSuggestions,
+    subscriptions: Subscriptions,
     mod_author_cache: Arc<RwLock<Vec<String>>>,
     runtime_api_cache: Arc<RwLock<api_runtime::RuntimeApiResponse>>,
     data_api_cache: Arc<RwLock<api_data::DataApiResponse>>,
+    placeholder_cache: Arc<RwLock<faq_placeholders::PlaceholderCache>>,
     mod_portal_credentials: Arc<ModPortalCredentials>,
 }
 
@@ -92,8 +113,11 @@ async fn main() {
     let faq_cache = Arc::new(RwLock::new(Vec::new()));
     let faq_cache_clone = faq_cache.clone();
 
-    let subscription_cache = Arc::new(RwLock::new(Vec::new()));
-    let subscription_cache_clone = subscription_cache.clone();
+    let faq_suggestions = FaqSuggestions::new(db.clone());
+    let faq_suggestions_clone = faq_suggestions.clone();
+
+    let subscriptions = Subscriptions::new(db.clone());
+    let subscriptions_clone = subscriptions.clone();
 
     let authorname_cache = Arc::new(RwLock::new(Vec::new()));
     let authorname_cache_clone = authorname_cache.clone();
@@ -126,19 +150,29 @@ async fn main() {
 
     // FrameworkOptions contains all of poise's configuration option in one struct
     // Every option can be omitted to use its default value
-    let options = poise::FrameworkOptions {
+    #[allow(unused_mut)]
+    let mut options = poise::FrameworkOptions {
         commands: vec![
             util::help(),
             util::get_server_info(),
+            server_commands::server(),
             util::reset_server_settings(),
             mod_commands::find_mod(),
             mod_commands::show_subscriptions(),
+            mod_commands::subscriptions(),
+            mod_commands::check_mod_list(),
+            modpack::modpack(),
+            mod_notify::my_mod_pings(),
             mod_commands::subscribe(),
             mod_commands::unsubscribe(),
             mod_commands::set_updates_channel(),
+            mod_commands::set_webhook_delivery(),
             mod_commands::set_modrole(),
             mod_commands::show_changelogs(),
+            outgoing_webhooks::outgoing_webhooks(),
+            notification_style::notify_style(),
             faq_commands::faq(),
+            faq_commands::faq_search(),
             faq_commands::faq_edit(),
             fff_commands::fff(),
             api_runtime::api(),
@@ -148,7 +182,7 @@ async fn main() {
             util::drop_faqs(),
         ],
         prefix_options: poise::PrefixFrameworkOptions {
-            prefix: Some("+".into()),
+            prefix: Some(PREFIX.into()),
             edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
                 Duration::from_secs(3600),
             ))),
@@ -172,17 +206,27 @@ async fn main() {
             Box::pin(async move {
                 if let serenity::FullEvent::GuildDelete { incomplete, full: _} = event {
                     if !incomplete.unavailable {
-                        util::on_guild_leave(incomplete.id, data.database.clone()).await?;
+                        util::on_guild_leave(incomplete.id, data.database.clone(), &data.subscriptions).await?;
                     }
                 }
                 if let serenity::FullEvent::Message { new_message } = event {
                     util::on_message(ctx.clone(), new_message, data).await?;
                 }
+                if let serenity::FullEvent::InteractionCreate { interaction: serenity::Interaction::Component(component) } = event {
+                    if component.data.custom_id.starts_with(mod_notify::NOTIFY_BUTTON_PREFIX) {
+                        mod_notify::on_notify_button(ctx, component, &data.database).await?;
+                    }
+                    if component.data.custom_id.starts_with(faq_suggest::SUGGESTION_BUTTON_PREFIX) {
+                        faq_suggest::on_suggestion_button(ctx, component, data).await?;
+                    }
+                }
                 Ok(())
             })
         },
         ..Default::default()
     };
+    #[cfg(feature = "feed-server")]
+    options.commands.push(feed_server::feed_url());
 
     let framework = poise::Framework::builder()
         .setup(move |ctx, ready, framework| {
@@ -193,10 +237,12 @@ async fn main() {
                     database: db_clone,
                     mod_cache: mods_cache_clone,
                     faq_cache: faq_cache_clone,
-                    mod_subscription_cache: subscription_cache_clone,
+                    faq_suggestions: faq_suggestions_clone,
+                    subscriptions: subscriptions_clone,
                     mod_author_cache: authorname_cache_clone,
                     runtime_api_cache: runtime_api_cache_clone,
                     data_api_cache: data_api_cache_clone,
+                    placeholder_cache: Arc::new(RwLock::new(faq_placeholders::PlaceholderCache::new())),
                     mod_portal_credentials: mod_portal_cred,
                 })
             })
@@ -225,6 +271,20 @@ async fn main() {
         }
     }
     
+    #[cfg(feature = "feed-server")]
+    {
+        let feed_address = var("FEED_SERVER_ADDRESS")
+            .unwrap_or_else(|_| "127.0.0.1:8080".to_owned())
+            .parse()
+            .expect("Invalid FEED_SERVER_ADDRESS, expected an address like 127.0.0.1:8080");
+        let feed_db = db.clone();
+        tokio::spawn(async move {
+            if let Err(error) = feed_server::run(feed_address, feed_db).await {
+                error!("Feed server stopped: {error}");
+            }
+        });
+    }
+
     let db_clone_2 = db.clone();
     let mut mod_update_interval = time::interval(time::Duration::from_secs(60));    // Update every minute
     tokio::spawn(async move {
@@ -238,6 +298,19 @@ async fn main() {
         }
     });
 
+    let db_clone_4 = db.clone();
+    let mut deprecation_interval = time::interval(time::Duration::from_hours(24));  // Run at startup, then once per day
+    tokio::spawn(async move {
+        loop {
+            deprecation_interval.tick().await;
+            match mods::update_deprecated_flags(&db_clone_4).await {
+                Ok(count) => info!("Updated deprecation of {count} mods"),
+                Err(error) => error!("Error while updating mod deprecation: {error}"),
+            }
+        };
+    });
+
+    let db_clone_3 = db.clone();
     let mut cache_update_interval = time::interval(time::Duration::from_secs(5*60));    // Update every 5 minutes
     tokio::spawn(async move {
         loop {
@@ -250,7 +323,11 @@ async fn main() {
                 Ok(()) => info!("Updated faq cache"),
                 Err(error) => error!("Error while updating faq cache: {error}"),
             };
-            match update_sub_cache(subscription_cache.clone(), db.clone()).await {
+            match faq_suggestions.reload().await {
+                Ok(()) => info!("Updated faq suggestion cache"),
+                Err(error) => error!("Error while updating faq suggestion cache: {error}"),
+            }
+            match subscriptions.reload().await {
                 Ok(()) => info!("Updated subscription cache"),
                 Err(error) => error!("Error while updating subscription cache: {error}"),
             };
@@ -275,6 +352,10 @@ async fn main() {
                 Ok(()) => info!("Updated API cache"),
                 Err(error) => error!("Error whille updating data api cache: {error}")
             }
+            match faq_stats::prune_faq_usage(&db_clone_3, chrono::Utc::now().timestamp()).await {
+                Ok(count) => info!("Pruned {count} old faq usage records"),
+                Err(error) => error!("Error while pruning faq usage: {error}"),
+            }
         };
     });
     
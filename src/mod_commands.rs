@@ -1,12 +1,14 @@
-use poise::serenity_prelude::{AutocompleteChoice, CreateEmbed, Colour};
+use std::{collections::HashSet, fmt::Write};
+use poise::serenity_prelude::{Attachment, AutocompleteChoice, ChannelId, CreateAllowedMentions, CreateAttachment, CreateEmbed, CreateEmbedFooter, Colour, Role};
 use poise::CreateReply;
-// use rust_fuzzy_search::fuzzy_search;
 use log::error;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
 
-use crate::mod_search_api;
+use crate::{mod_list, mod_search_api};
 use crate::{Context, Error, custom_errors::CustomError, Data, SEPARATOR,
-    util::{get_subscribed_authors, get_subscribed_mods, is_mod, get_server_id},
-    mods::{self, SubCacheEntry, SubscriptionType}
+    util::{embed_list_field, escape_formatting, is_mod, get_server_id, paginate_embeds},
+    mods, subscriptions::SubscriptionType
 };
 
 enum AutocompleteType{
@@ -25,14 +27,26 @@ pub async fn set_updates_channel(
     let server_id = channel.guild_id.get() as i64;
     let db = &ctx.data().database;
 
-    if (sqlx::query!(r#"SELECT * FROM servers WHERE server_id = $1"#, server_id)
+    if let Some(server) = sqlx::query!(r#"SELECT webhook_url FROM servers WHERE server_id = $1"#, server_id)
         .fetch_optional(db)
-        .await?).is_some() {
-        // Update server data if it does exist
-        sqlx::query!(r#"UPDATE servers SET updates_channel = $1 WHERE server_id = $2"#,
-        channel_id, server_id)
-            .execute(db)
-            .await?;
+        .await? {
+        // Move the update webhook along to the new channel. It is created before anything changes,
+        // so a missing permission in the new channel leaves the old setup working.
+        if let Some(old_url) = server.webhook_url {
+            let http = &ctx.serenity_context().http;
+            let url = mods::create_update_webhook(channel.id, http).await?;
+            sqlx::query!(r#"UPDATE servers SET updates_channel = $1, webhook_url = $2 WHERE server_id = $3"#,
+            channel_id, url, server_id)
+                .execute(db)
+                .await?;
+            mods::delete_update_webhook(&old_url, http).await;
+        } else {
+            // Update server data if it does exist
+            sqlx::query!(r#"UPDATE servers SET updates_channel = $1 WHERE server_id = $2"#,
+            channel_id, server_id)
+                .execute(db)
+                .await?;
+        }
     } else {
         // Add server and set setting if it does not exist
         sqlx::query!(r#"INSERT INTO servers (server_id, updates_channel) VALUES ($1, $2)"#,
@@ -46,6 +60,48 @@ pub async fn set_updates_channel(
     Ok(())
 }
 
+/// Send mod updates through a webhook, optionally with a custom name and avatar.
+#[allow(clippy::cast_sign_loss)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings")]
+pub async fn set_webhook_delivery(
+    ctx: Context<'_>,
+    #[description = "Send mod updates through a webhook"]
+    enabled: bool,
+    #[description = "Name shown on mod update messages"]
+    name: Option<String>,
+    #[description = "Link to the avatar shown on mod update messages"]
+    avatar_url: Option<String>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let http = &ctx.serenity_context().http;
+    let server = sqlx::query!(r#"SELECT updates_channel, webhook_url FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await?;
+    let (updates_channel, old_url) = server.map_or((None, None), |s| (s.updates_channel, s.webhook_url));
+
+    // Replace any existing webhook rather than leaving it behind in the channel
+    if let Some(url) = old_url {
+        mods::delete_update_webhook(&url, http).await;
+        mods::clear_update_webhook(db, server_id).await?;
+    }
+    if !enabled {
+        ctx.say("Mod updates will be sent as regular messages.").await?;
+        return Ok(())
+    }
+
+    let Some(channel_id) = updates_channel else {
+        return Err(Box::new(CustomError::new("No updates channel set. Use /set_updates_channel first.")))
+    };
+    let url = mods::create_update_webhook(ChannelId::new(channel_id as u64), http).await?;
+    sqlx::query!(r#"UPDATE servers SET webhook_url = $1, webhook_name = $2, webhook_avatar = $3 WHERE server_id = $4"#,
+        url, name, avatar_url, server_id)
+        .execute(db)
+        .await?;
+    ctx.say(format!("Mod updates in <#{channel_id}> will now be sent through a webhook.")).await?;
+    Ok(())
+}
+
 /// Set which role is allowed to edit bot settings. Admins can always edit settings.
 #[allow(clippy::cast_possible_wrap)]
 #[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings")]
@@ -135,30 +191,25 @@ pub async fn subscribe_mod(
     #[description = "Name of the mod to subscribe to"]
     #[autocomplete = "autocomplete_modname"]
     modname: String,
+    #[description = "Role to ping when this mod updates"]
+    ping_role: Option<Role>,
 ) -> Result<(), Error> {
     let Some(server) = ctx.guild_id() else {
         return Err(Box::new(CustomError::new("Could not get server ID")))
     };
     let server_id = server.get() as i64;
     let db = &ctx.data().database;
-
-    sqlx::query!(r#"INSERT OR REPLACE INTO subscribed_mods (server_id, mod_name) VALUES ($1, $2)"#, server_id, modname)
-        .execute(db)
-        .await?;
-    ctx.say(format!("Mod {modname} added to subscriptions")).await?;
-
-    let cache = &ctx.data().mod_subscription_cache;
-    match cache.write() {
-        Ok(mut c) => c.push(
-            SubCacheEntry{
-                server_id,
-                subscription: SubscriptionType::Modname(modname),
-            }
-        ),
-        Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
-        }
+    let role_id = ping_role.as_ref().map(|r| r.id.get() as i64);
+    let mod_names = get_mod_names(db).await?;
+    if !mod_names.contains(&modname) {
+        return Err(Box::new(CustomError::new(&not_found_message("Mod", &modname, &mod_names))))
     }
+
+    ctx.data().subscriptions.subscribe_mod(server_id, &modname, role_id).await?;
+    let response = ping_role.as_ref().map_or_else(
+        || format!("Mod {modname} added to subscriptions"),
+        |role| format!("Mod {modname} added to subscriptions, pinging {role} on updates"));
+    ctx.send(CreateReply::default().content(response).allowed_mentions(CreateAllowedMentions::new())).await?;
     Ok(())
 }
 
@@ -176,10 +227,9 @@ pub async fn unsubscribe_mod(
         return Err(Box::new(CustomError::new("Could not get server ID")))
     };
     let server_id = server.get() as i64;
-    let db = &ctx.data().database;
-    sqlx::query!(r#"DELETE FROM subscribed_mods WHERE server_id = $1 AND mod_name = $2"#, server_id, modname)
-        .execute(db)
-        .await?;
+    if !ctx.data().subscriptions.unsubscribe_mod(server_id, &modname).await? {
+        return Err(Box::new(CustomError::new(&format!("Not subscribed to mod {modname}"))))
+    }
     let response = format!("Mod {modname} removed from subscriptions");
     ctx.say(response).await?;
     Ok(())
@@ -201,31 +251,24 @@ pub async fn subscribe_author(
     #[description = "Name of the mod author to subscribe to"]
     #[autocomplete = "autocomplete_author"]
     author: String,
+    #[description = "Role to ping when this author updates a mod"]
+    ping_role: Option<Role>,
 ) -> Result<(), Error> {
     let Some(server) = ctx.guild_id() else {
         return Err(Box::new(CustomError::new("Could not get server ID")))
     };
     let server_id = server.get() as i64;
-    let db = &ctx.data().database;
-
-    sqlx::query!(r#"INSERT OR REPLACE INTO subscribed_authors (server_id, author_name) VALUES ($1, $2)"#, server_id, author)
-        .execute(db)
-        .await?;
-    let response = format!("Author {author} added to subscriptions");
-    ctx.say(response).await?;
-
-    let cache = &ctx.data().mod_subscription_cache;
-    match cache.write() {
-        Ok(mut c) => c.push(
-            SubCacheEntry{
-                server_id,
-                subscription: SubscriptionType::Author(author),
-            }
-        ),
-        Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
-        }
+    let role_id = ping_role.as_ref().map(|r| r.id.get() as i64);
+    let authors = get_author_names(ctx)?;
+    if !authors.contains(&author) {
+        return Err(Box::new(CustomError::new(&not_found_message("Author", &author, &authors))))
     }
+
+    ctx.data().subscriptions.subscribe_author(server_id, &author, role_id).await?;
+    let response = ping_role.as_ref().map_or_else(
+        || format!("Author {author} added to subscriptions"),
+        |role| format!("Author {author} added to subscriptions, pinging {role} on updates"));
+    ctx.send(CreateReply::default().content(response).allowed_mentions(CreateAllowedMentions::new())).await?;
     Ok(())
 }
 
@@ -260,10 +303,9 @@ pub async fn unsubscribe_author(
         return Err(Box::new(CustomError::new("Could not get server ID")))
     };
     let server_id = server.get() as i64;
-    let db = &ctx.data().database;
-    sqlx::query!(r#"DELETE FROM subscribed_authors WHERE server_id = $1 AND author_name = $2"#, server_id, author)
-        .execute(db)
-        .await?;
+    if !ctx.data().subscriptions.unsubscribe_author(server_id, &author).await? {
+        return Err(Box::new(CustomError::new(&format!("Not subscribed to author {author}"))))
+    }
     let response = format!("Author {author} removed from subscriptions");
     ctx.say(response).await?;
     Ok(())
@@ -282,22 +324,21 @@ fn autocomplete_unsubscribe(
     partial: &str,
     data_type: &AutocompleteType,
 ) -> Vec<String> {
-    let cache = &ctx.data().mod_subscription_cache;
     let Some(server) = ctx.guild_id() else {
         error!("Could not get server ID while autocompleting faq name"); 
         return vec![]
     };
     let server_id = server.get() as i64;
-    let subscription_cache = match cache.read(){
+    let subscription_cache = match ctx.data().subscriptions.cached() {
         Ok(c) => c,
         Err(e) => {
-            error!{"Error acquiring cache: {e}"}
+            error!{"{e}"}
             return vec![]
         },
     };
     match data_type {
         AutocompleteType::Mod => {
-            subscription_cache.clone()
+            subscription_cache
                 .into_iter()
                 .filter(|entry| entry.server_id == server_id)
                 .filter_map(|entry| match entry.subscription {
@@ -308,7 +349,7 @@ fn autocomplete_unsubscribe(
                 .collect::<Vec<String>>()
         },
         AutocompleteType::Author => {
-            subscription_cache.clone()
+            subscription_cache
                 .into_iter()
                 .filter(|entry| entry.server_id == server_id)
                 .filter_map(|entry| match entry.subscription {
@@ -321,6 +362,9 @@ fn autocomplete_unsubscribe(
     }
 }
 
+// Subscriptions listed on a single page of /show_subscriptions
+const SUBSCRIPTIONS_PER_PAGE: usize = 10;
+
 /// List which mods and authors the server is currently subscribed to.
 #[allow(clippy::unused_async, clippy::cast_possible_wrap)]
 #[poise::command(prefix_command, slash_command, guild_only, category="Subscriptions")]
@@ -331,29 +375,428 @@ pub async fn show_subscriptions(
         return Err(Box::new(CustomError::new("Could not get server ID")))
     };
     let server_id = server.get() as i64;
-    let db = &ctx.data().database;
+    let subscriptions = &ctx.data().subscriptions;
+    let mods = subscriptions.get_mod_details(server_id).await?;
+    let authors = subscriptions.get_author_details(server_id).await?;
+    let title = format!("Subscriptions: {} mods, {} authors", mods.len(), authors.len());
+    if mods.is_empty() && authors.is_empty() {
+        let embed = CreateEmbed::new()
+            .title(title)
+            .description("Not subscribed to any mods or authors, all mod updates are shown");
+        ctx.send(CreateReply::default().embed(embed)).await?;
+        return Ok(())
+    }
 
-    let subscribed_mods_vec = get_subscribed_mods(db, server_id)
-        .await?;
-    let subscribed_mods = if subscribed_mods_vec.is_empty() {
-        String::from("_None_")
+    let mut mod_lines = Vec::new();
+    for m in mods {
+        let url = format!("https://mods.factorio.com/mod/{}", m.name).replace(' ', "%20");
+        let mut line = match m.title {
+            Some(title) => format!("**[{}]({url})**", escape_formatting(&title).await),
+            None => format!("**{}** _(not on the mod portal)_", escape_formatting(&m.name).await),
+        };
+        if let Some(version) = m.version.filter(|v| !v.is_empty()) {
+            let _ = write!(line, " {version}");
+        }
+        if let Some(released_at) = m.released_at.filter(|t| *t > 0) {
+            let _ = write!(line, ", updated <t:{released_at}:R>");
+        }
+        line.push_str(&format_ping_role(m.ping_role));
+        mod_lines.push(line);
+    }
+    let mut author_lines = Vec::new();
+    for a in authors {
+        let url = format!("https://mods.factorio.com/user/{}", a.name);
+        let mut line = format!("**[{}]({url})**, {} mods", escape_formatting(&a.name).await, a.mod_count);
+        line.push_str(&format_ping_role(a.ping_role));
+        author_lines.push(line);
+    }
+
+    let pages = mod_lines.chunks(SUBSCRIPTIONS_PER_PAGE)
+        .map(|chunk| format!("**Mods**\n{}", chunk.join("\n")))
+        .chain(author_lines.chunks(SUBSCRIPTIONS_PER_PAGE)
+            .map(|chunk| format!("**Authors**\n{}", chunk.join("\n"))))
+        .map(|description| CreateEmbed::new()
+            .title(&title)
+            .description(description)
+            .color(Colour::from_rgb(0x58, 0x65, 0xF2)))
+        .collect::<Vec<CreateEmbed>>();
+    paginate_embeds(ctx, pages).await
+}
+
+fn format_ping_role(ping_role: Option<i64>) -> String {
+    ping_role.map_or_else(String::new, |role| format!(", pings <@&{role}>"))
+}
+
+/// Names of all mods on the mod portal, including those for older Factorio versions
+async fn get_mod_names(db: &Pool<Sqlite>) -> Result<HashSet<String>, Error> {
+    Ok(sqlx::query!(r#"SELECT name FROM mods"#)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|rec| rec.name)
+        .collect())
+}
+
+// Fails while the cache is still empty after startup, so authors are not mistaken for unknown ones
+fn get_author_names(ctx: Context<'_>) -> Result<HashSet<String>, Error> {
+    match ctx.data().mod_author_cache.read() {
+        Ok(c) if c.is_empty() => Err(Box::new(CustomError::new("The list of mod authors is still loading, try again in a few minutes"))),
+        Ok(c) => Ok(c.iter().cloned().collect()),
+        Err(e) => Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}")))),
+    }
+}
+
+// Error message for an unknown mod or author, with the closest known names as suggestions
+fn not_found_message(kind: &str, name: &str, candidates: &HashSet<String>) -> String {
+    let candidates = candidates.iter().map(String::as_str).collect::<Vec<&str>>();
+    let suggestions = rust_fuzzy_search::fuzzy_search_best_n(name, &candidates, 3)
+        .into_iter()
+        .filter(|(_, score)| *score > 0.4)
+        .map(|(suggestion, _)| format!("`{suggestion}`"))
+        .collect::<Vec<String>>();
+    if suggestions.is_empty() {
+        format!("{kind} `{name}` not found on the mod portal")
     } else {
-        subscribed_mods_vec.join("\n")
+        format!("{kind} `{name}` not found on the mod portal. Did you mean {}?", suggestions.join(", "))
+    }
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+pub enum ExportFormat {
+    #[name = "JSON"]
+    Json,
+    #[name = "Text"]
+    Text,
+}
+
+/// Subscriptions as exported by `/subscriptions export`. Also reads Factorio's mod-list.json.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct SubscriptionList {
+    #[serde(default)]
+    mods: Vec<SubscriptionListEntry>,
+    #[serde(default)]
+    authors: Vec<SubscriptionListEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SubscriptionListEntry {
+    name: String,
+    #[serde(default = "mod_list::default_enabled", skip_serializing)]
+    enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ping_role: Option<u64>,
+}
+
+impl SubscriptionList {
+    /// Drop names listed more than once, keeping the first entry of each
+    fn dedup(&mut self) {
+        let mut seen = HashSet::new();
+        self.mods.retain(|m| seen.insert(m.name.clone()));
+        seen.clear();
+        self.authors.retain(|a| seen.insert(a.name.clone()));
+    }
+}
+
+impl SubscriptionListEntry {
+    const fn new(name: String, ping_role: Option<u64>) -> Self {
+        Self { name, enabled: true, ping_role }
+    }
+}
+
+// Plain text lists: one `mod: <name>` or `author: <name>` per line, bare names are mods
+fn parse_subscription_text(contents: &str) -> SubscriptionList {
+    let mut list = SubscriptionList::default();
+    for line in contents.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        if let Some(author) = line.strip_prefix("author:") {
+            list.authors.push(SubscriptionListEntry::new(author.trim().to_owned(), None));
+        } else {
+            let name = line.strip_prefix("mod:").unwrap_or(line).trim();
+            list.mods.push(SubscriptionListEntry::new(name.to_owned(), None));
+        }
+    }
+    list
+}
+
+/// Export or import the server's subscriptions in bulk
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", subcommands("export_subscriptions", "import_subscriptions", "cleanup_subscriptions"), subcommand_required, category="Subscriptions")]
+pub async fn subscriptions(
+    _: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Download this server's subscribed mods and authors as a file
+#[allow(clippy::cast_sign_loss)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", rename="export")]
+pub async fn export_subscriptions(
+    ctx: Context<'_>,
+    #[description = "File format, JSON by default"]
+    format: Option<ExportFormat>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let subscriptions = &ctx.data().subscriptions;
+    let list = SubscriptionList {
+        mods: subscriptions.get_mods(server_id).await?
+            .into_iter()
+            .map(|(name, role)| SubscriptionListEntry::new(name, role.map(|r| r as u64)))
+            .collect(),
+        authors: subscriptions.get_authors(server_id).await?
+            .into_iter()
+            .map(|(name, role)| SubscriptionListEntry::new(name, role.map(|r| r as u64)))
+            .collect(),
+    };
+    let summary = format!("{} mods and {} authors", list.mods.len(), list.authors.len());
+    let attachment = match format.unwrap_or(ExportFormat::Json) {
+        ExportFormat::Json => CreateAttachment::bytes(serde_json::to_vec_pretty(&list)?, "subscriptions.json"),
+        ExportFormat::Text => {
+            let text = list.mods.iter()
+                .map(|m| format!("mod: {}", m.name))
+                .chain(list.authors.iter().map(|a| format!("author: {}", a.name)))
+                .collect::<Vec<String>>()
+                .join("\n");
+            CreateAttachment::bytes(text, "subscriptions.txt")
+        },
     };
+    ctx.send(CreateReply::default().content(format!("Exported {summary}")).attachment(attachment)).await?;
+    Ok(())
+}
 
-    let subscribed_authors_vec = get_subscribed_authors(db, server_id)
-        .await?;
-    let subscribed_authors = if subscribed_authors_vec.is_empty() {
-        String::from("_None_")
+/// Subscribe to every mod and author in an exported subscription list or a mod-list.json
+#[allow(clippy::cast_possible_wrap)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", rename="import")]
+pub async fn import_subscriptions(
+    ctx: Context<'_>,
+    #[description = "Exported subscriptions (.json or .txt) or a mod-list.json"]
+    file: Attachment,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let subscriptions = &ctx.data().subscriptions;
+    let contents = mod_list::download_text_attachment(&file).await?;
+    let mut list = if contents.trim_start().starts_with('{') {
+        let Ok(list) = serde_json::from_str::<SubscriptionList>(&contents) else {
+            return Err(Box::new(CustomError::new("Could not read file, expected an exported subscription list or a mod-list.json")))
+        };
+        list
     } else {
-        subscribed_authors_vec.join("\n")
+        parse_subscription_text(&contents)
     };
+    list.dedup();
+
+    let known_mods = get_mod_names(db).await?;
+    let known_authors = get_author_names(ctx)?;
+    // Ping roles only carry over when importing into the server they were exported from
+    let valid_roles = ctx.guild()
+        .map(|g| g.roles.keys().map(|r| r.get()).collect::<HashSet<u64>>())
+        .unwrap_or_default();
+    let existing_mods = subscriptions.get_mods(server_id).await?
+        .into_iter().map(|(name, _)| name).collect::<HashSet<String>>();
+    let existing_authors = subscriptions.get_authors(server_id).await?
+        .into_iter().map(|(name, _)| name).collect::<HashSet<String>>();
+
+    let mut unknown = Vec::new();
+    let mut already_subscribed = 0;
+    let mut added_mods = 0;
+    let mut added_authors = 0;
+    for entry in list.mods.into_iter().filter(|m| m.enabled && !mod_list::is_builtin(&m.name)) {
+        if !known_mods.contains(&entry.name) {
+            unknown.push(entry.name);
+            continue;
+        }
+        if existing_mods.contains(&entry.name) {
+            already_subscribed += 1;
+            continue;
+        }
+        let role = entry.ping_role.filter(|r| valid_roles.contains(r)).map(|r| r as i64);
+        subscriptions.subscribe_mod(server_id, &entry.name, role).await?;
+        added_mods += 1;
+    }
+    for entry in list.authors {
+        if !known_authors.contains(&entry.name) {
+            unknown.push(format!("{} (author)", entry.name));
+            continue;
+        }
+        if existing_authors.contains(&entry.name) {
+            already_subscribed += 1;
+            continue;
+        }
+        let role = entry.ping_role.filter(|r| valid_roles.contains(r)).map(|r| r as i64);
+        subscriptions.subscribe_author(server_id, &entry.name, role).await?;
+        added_authors += 1;
+    }
 
-    let response = format!("**Subscribed mods:**\n{subscribed_mods}\n**Subscribed authors:**\n{subscribed_authors}");
+    let mut response = format!("Subscribed to {added_mods} mods and {added_authors} authors, {already_subscribed} already subscribed.");
+    if !unknown.is_empty() {
+        let mut unknown_list = String::new();
+        for name in &unknown {
+            if unknown_list.len() + name.len() > 1500 {
+                unknown_list.push_str("...");
+                break;
+            }
+            let _ = write!(unknown_list, "`{name}`, ");
+        }
+        let unknown_list = unknown_list.trim_end_matches(", ");
+        let _ = write!(response, "\nNot found on the mod portal ({}): {unknown_list}", unknown.len());
+    }
     ctx.say(response).await?;
     Ok(())
 }
 
+/// List subscriptions to mods and authors that are no longer on the mod portal
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", rename="cleanup")]
+pub async fn cleanup_subscriptions(
+    ctx: Context<'_>,
+    #[description = "Unsubscribe from the entries found"]
+    remove: Option<bool>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let subscriptions = &ctx.data().subscriptions;
+    // Compare against the current mod portal, as the mods table keeps mods that were removed from it
+    ctx.defer().await?;
+    let portal_mods = mods::get_mods(1, true).await?.results;
+    if portal_mods.is_empty() {
+        return Err(Box::new(CustomError::new("Could not load the list of mods from the mod portal, try again later")))
+    }
+    let known_mods = portal_mods.iter().map(|m| m.name.clone()).collect::<HashSet<String>>();
+    let known_authors = portal_mods.into_iter().map(|m| m.owner).collect::<HashSet<String>>();
+    let stale_mods = subscriptions.get_mods(server_id).await?
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| !known_mods.contains(name))
+        .collect::<Vec<String>>();
+    let stale_authors = subscriptions.get_authors(server_id).await?
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| !known_authors.contains(name))
+        .collect::<Vec<String>>();
+    if stale_mods.is_empty() && stale_authors.is_empty() {
+        ctx.say("All subscriptions point to existing mods and authors").await?;
+        return Ok(())
+    }
+
+    let remove = remove.unwrap_or(false);
+    if remove {
+        for name in &stale_mods {
+            subscriptions.unsubscribe_mod(server_id, name).await?;
+        }
+        for name in &stale_authors {
+            subscriptions.unsubscribe_author(server_id, name).await?;
+        }
+    }
+    let mut embed = CreateEmbed::new()
+        .title(if remove { "Removed subscriptions" } else { "Subscriptions not found on the mod portal" })
+        .color(Colour::ORANGE);
+    if !stale_mods.is_empty() {
+        embed = embed.field(format!("Mods ({})", stale_mods.len()), embed_list_field(&stale_mods), false);
+    }
+    if !stale_authors.is_empty() {
+        embed = embed.field(format!("Authors ({})", stale_authors.len()), embed_list_field(&stale_authors), false);
+    }
+    if !remove {
+        embed = embed.footer(CreateEmbedFooter::new("Run again with remove set to true to unsubscribe from these"));
+    }
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Check a mod-list.json, save file or list of mods for outdated, deprecated and unavailable mods
+#[allow(clippy::cast_possible_wrap)]
+#[poise::command(prefix_command, slash_command, guild_only)]
+pub async fn check_mod_list(
+    ctx: Context<'_>,
+    #[description = "mod-list.json, a save file, or a text file with one mod per line (optionally with version)"]
+    file: Attachment,
+    #[description = "Factorio version to check against, latest by default"]
+    factorio_version: Option<String>,
+    #[description = "Subscribe this server to all listed mods (moderators only)"]
+    subscribe: Option<bool>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let subscribe = subscribe.unwrap_or(false);
+    if subscribe && !is_mod(ctx).await? {
+        return Err(Box::new(CustomError::new("Only moderators can subscribe the server to mods")))
+    }
+    // Save files can take a while to download
+    ctx.defer().await?;
+    let entries = mod_list::download_mod_list(&file).await?;
+    if entries.is_empty() {
+        return Err(Box::new(CustomError::new("No enabled mods found in this list")))
+    }
+
+    let factorio_version = match factorio_version {
+        Some(v) => v,
+        None => sqlx::query!(r#"SELECT DISTINCT factorio_version FROM mods"#)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .filter_map(|rec| rec.factorio_version)
+            .max_by_key(|v| mod_list::parse_version(v).unwrap_or_default())
+            .unwrap_or_default(),
+    };
+
+    let mut outdated = Vec::new();
+    let mut deprecated = Vec::new();
+    let mut wrong_version = Vec::new();
+    let mut unknown = Vec::new();
+    let mut known = Vec::new();
+    for entry in &entries {
+        let Some(record) = sqlx::query!(r#"SELECT version, factorio_version, deprecated FROM mods WHERE name = $1"#, entry.name)
+            .fetch_optional(db)
+            .await? else {
+            unknown.push(format!("`{}`", entry.name));
+            continue;
+        };
+        known.push(entry.name.clone());
+        if record.deprecated {
+            deprecated.push(format!("`{}`", entry.name));
+        }
+        if record.factorio_version.as_deref() != Some(factorio_version.as_str()) {
+            wrong_version.push(format!("`{}` ({})", entry.name, record.factorio_version.unwrap_or_default()));
+        }
+        let latest = record.version.unwrap_or_default();
+        let is_outdated = entry.version.as_deref()
+            .and_then(mod_list::parse_version)
+            .zip(mod_list::parse_version(&latest))
+            .is_some_and(|(listed, latest)| listed < latest);
+        if is_outdated {
+            outdated.push(format!("`{}` {} → {latest}", entry.name, entry.version.clone().unwrap_or_default()));
+        }
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("Checked {} mods", entries.len()))
+        .color(Colour::ORANGE);
+    if outdated.is_empty() && deprecated.is_empty() && wrong_version.is_empty() && unknown.is_empty() {
+        embed = embed.description(format!("All mods are up to date and available for Factorio {factorio_version}"));
+    }
+    for (title, items) in [
+        ("Outdated".to_owned(), &outdated),
+        ("Deprecated".to_owned(), &deprecated),
+        (format!("Not updated for Factorio {factorio_version}"), &wrong_version),
+        ("Not found on the mod portal".to_owned(), &unknown),
+    ] {
+        if !items.is_empty() {
+            embed = embed.field(format!("{title} ({})", items.len()), embed_list_field(items), false);
+        }
+    }
+
+    if subscribe {
+        let subscriptions = &ctx.data().subscriptions;
+        let existing = subscriptions.get_mods(server_id).await?
+            .into_iter().map(|(name, _)| name).collect::<HashSet<String>>();
+        let mut added = 0;
+        for name in known.iter().filter(|name| !existing.contains(*name)) {
+            subscriptions.subscribe_mod(server_id, name, None).await?;
+            added += 1;
+        }
+        embed = embed.footer(CreateEmbedFooter::new(format!("Subscribed to {added} new mods")));
+    }
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
 /// Find a mod on the mod portal.
 #[allow(clippy::unused_async)]
 #[poise::command(prefix_command, slash_command, track_edits, rename="mod", aliases("find-mod", "find_mod"))]
@@ -474,3 +917,30 @@ async fn autocomplete_modname<'a>(
 
     list
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn names(entries: &[SubscriptionListEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_subscription_text() {
+        let list = parse_subscription_text("# Exported subscriptions\nmod: flib\n\n  Krastorio2  \nauthor: raiguard\nmod:  Rail signal planner\n");
+        assert_eq!(names(&list.mods), vec!["flib", "Krastorio2", "Rail signal planner"]);
+        assert_eq!(names(&list.authors), vec!["raiguard"]);
+        assert!(list.mods.iter().all(|m| m.enabled && m.ping_role.is_none()));
+        assert!(parse_subscription_text("\n# nothing here\n").mods.is_empty());
+    }
+
+    #[test]
+    fn dedups_subscription_list() {
+        let mut list = parse_subscription_text("mod: flib\nflib\nauthor: raiguard\nauthor: raiguard\nKrastorio2\nmod: flib");
+        list.dedup();
+        assert_eq!(names(&list.mods), vec!["flib", "Krastorio2"]);
+        assert_eq!(names(&list.authors), vec!["raiguard"]);
+    }
+}
@@ -0,0 +1,305 @@
+use std::{collections::HashSet, io::{BufRead, BufReader, Cursor, Read}};
+use flate2::read::ZlibDecoder;
+use poise::serenity_prelude::Attachment;
+use serde::Deserialize;
+
+use crate::{Error, custom_errors::CustomError};
+
+/// Mods shipped with the game, listed in mod-list.json but not on the mod portal
+pub const BUILTIN_MODS: [&str; 4] = ["base", "elevated-rails", "quality", "space-age"];
+
+// Largest attachment accepted as a mod list
+const MAX_ATTACHMENT_SIZE: u32 = 1024 * 1024;
+
+// Largest save file accepted, saves are read in memory
+const MAX_SAVE_SIZE: u32 = 50 * 1024 * 1024;
+
+// Files of a save starting with the header that holds the mod list, by game version from new to old
+const SAVE_HEADER_FILES: [&str; 3] = ["level-init.dat", "level.dat0", "level.dat"];
+
+// Bytes of the header searched for the mod list, which comes before any map data
+const SAVE_HEADER_LENGTH: u64 = 1024 * 1024;
+
+/// Factorio's mod-list.json
+#[derive(Deserialize, Debug)]
+pub struct ModList {
+    pub mods: Vec<ModListEntry>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ModListEntry {
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+// Mods without an `enabled` field in mod-list.json are enabled
+pub const fn default_enabled() -> bool {
+    true
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTIN_MODS.contains(&name)
+}
+
+/// Download a text attachment such as a mod list.
+pub async fn download_text_attachment(attachment: &Attachment) -> Result<String, Error> {
+    if attachment.size > MAX_ATTACHMENT_SIZE {
+        return Err(Box::new(CustomError::new(&format!("{} is too large, files can be at most 1 MB", attachment.filename))))
+    }
+    let content = attachment.download().await?;
+    String::from_utf8(content).map_err(|_| -> Error {
+        Box::new(CustomError::new(&format!("{} is not a text file", attachment.filename)))
+    })
+}
+
+/// Download a mod list and read its enabled, non-builtin mods.
+/// Takes the same formats as [`parse_mod_list`], and save files (`.zip`).
+pub async fn download_mod_list(attachment: &Attachment) -> Result<Vec<ModListEntry>, Error> {
+    if !attachment.filename.to_lowercase().ends_with(".zip") {
+        return parse_mod_list(&download_text_attachment(attachment).await?)
+    }
+    if attachment.size > MAX_SAVE_SIZE {
+        return Err(Box::new(CustomError::new(&format!("{} is too large, save files can be at most 50 MB", attachment.filename))))
+    }
+    let content = attachment.download().await?;
+    let mut entries = read_save_mod_list(&content)?;
+    entries.retain(|entry| !is_builtin(&entry.name));
+    Ok(entries)
+}
+
+/// Mods of a save file, read from the header of its level data
+pub fn read_save_mod_list(save: &[u8]) -> Result<Vec<ModListEntry>, Error> {
+    let not_a_save = || -> Error { Box::new(CustomError::new("Could not read file, expected a Factorio save file")) };
+    let mut archive = zip::ZipArchive::new(Cursor::new(save)).map_err(|_| not_a_save())?;
+    let Some(path) = SAVE_HEADER_FILES.iter().find_map(|file| {
+        archive.file_names().find(|name| name.rsplit('/').next() == Some(file)).map(str::to_owned)
+    }) else {
+        return Err(not_a_save())
+    };
+    let mut reader = BufReader::new(archive.by_name(&path).map_err(|_| not_a_save())?);
+    // Newer saves compress their level data with zlib, older ones only rely on the zip compression
+    let zlib = reader.fill_buf()?.first() == Some(&0x78);
+    let mut header = Vec::new();
+    if zlib {
+        ZlibDecoder::new(reader).take(SAVE_HEADER_LENGTH).read_to_end(&mut header)?;
+    } else {
+        reader.take(SAVE_HEADER_LENGTH).read_to_end(&mut header)?;
+    }
+    parse_save_mods(&header)
+        .ok_or_else(|| -> Error { Box::new(CustomError::new("Could not find the mod list in this save file")) })
+}
+
+// Reads the little endian, space optimized values of Factorio's save format
+struct SaveReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SaveReader<'a> {
+    fn bytes(&mut self, count: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(count)?)?;
+        self.pos += count;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    // Values below 255 take a single byte, larger ones are written after a 255 byte
+    fn optimized_u16(&mut self) -> Option<u16> {
+        match self.u8()? {
+            0xFF => self.u16(),
+            b => Some(u16::from(b)),
+        }
+    }
+
+    fn optimized_u32(&mut self) -> Option<u32> {
+        match self.u8()? {
+            0xFF => self.u32(),
+            b => Some(u32::from(b)),
+        }
+    }
+
+    fn string(&mut self) -> Option<&'a str> {
+        let length = self.optimized_u32()?;
+        std::str::from_utf8(self.bytes(usize::try_from(length).ok()?)?).ok()
+    }
+}
+
+fn is_mod_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 100 && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+}
+
+// Read a mod list of `count` mods, each a name, a version and a CRC
+fn read_save_mods(reader: &mut SaveReader) -> Option<Vec<ModListEntry>> {
+    let count = reader.optimized_u32()?;
+    if count == 0 || count > 10_000 {
+        return None
+    }
+    (0..count).map(|_| {
+        let name = reader.string().filter(|name| is_mod_name(name))?;
+        let version = format!("{}.{}.{}", reader.optimized_u16()?, reader.optimized_u16()?, reader.optimized_u16()?);
+        reader.u32()?;
+        Some(ModListEntry { name: name.to_owned(), enabled: true, version: Some(version) })
+    }).collect()
+}
+
+/// Find the mod list in a save header. The fields before it changed between game versions,
+/// so this looks for a list starting with `base`, which every save has, that reads cleanly to the end.
+fn parse_save_mods(header: &[u8]) -> Option<Vec<ModListEntry>> {
+    const BASE: &[u8] = b"\x04base";
+    header.windows(BASE.len())
+        .enumerate()
+        .filter(|(_, window)| *window == BASE)
+        .find_map(|(pos, _)| {
+            // The mod count takes one byte, or five if there are 255 mods or more
+            [1, 5].into_iter().filter_map(|size| pos.checked_sub(size)).find_map(|start| {
+                let mut reader = SaveReader { data: header, pos: start };
+                read_save_mods(&mut reader)
+            })
+        })
+}
+
+/// Split a version string like `1.2.3` into its numeric parts for comparison.
+pub fn parse_version(version: &str) -> Option<Vec<u32>> {
+    version.trim().split('.').map(|part| part.parse::<u32>().ok()).collect()
+}
+
+// Parse `name`, `name version` or a mod zip file name like `name_1.2.3.zip`.
+// Mod names can contain spaces, so only a trailing version-like part is split off.
+fn parse_text_entry(line: &str) -> ModListEntry {
+    let line = line.strip_suffix(".zip").unwrap_or(line);
+    let split = line.rsplit_once(' ')
+        .or_else(|| line.rsplit_once('_'))
+        .filter(|(_, version)| version.contains('.') && parse_version(version).is_some());
+    let (name, version) = match split {
+        Some((name, version)) => (name.trim(), Some(version.to_owned())),
+        None => (line, None),
+    };
+    ModListEntry { name: name.to_owned(), enabled: true, version }
+}
+
+/// Enabled, non-builtin mods in either a mod-list.json or a list with one mod per line.
+/// Lines may include a version (`name 1.2.3`) or be mod file names (`name_1.2.3.zip`).
+pub fn parse_mod_list(contents: &str) -> Result<Vec<ModListEntry>, Error> {
+    let mut entries = if contents.trim_start().starts_with('{') {
+        let Ok(mod_list) = serde_json::from_str::<ModList>(contents) else {
+            return Err(Box::new(CustomError::new("Could not read mod list, expected the format of mod-list.json")))
+        };
+        mod_list.mods.into_iter()
+            .filter(|m| m.enabled)
+            .collect::<Vec<ModListEntry>>()
+    } else {
+        contents.split(['\n', ','])
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(parse_text_entry)
+            .collect::<Vec<ModListEntry>>()
+    };
+    let mut seen = HashSet::new();
+    entries.retain(|entry| !is_builtin(&entry.name) && seen.insert(entry.name.clone()));
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn names(entries: &[ModListEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_mod_list_json() {
+        let json = r#"{"mods": [
+            {"name": "base", "enabled": true},
+            {"name": "space-age", "enabled": true},
+            {"name": "flib", "enabled": true, "version": "0.12.9"},
+            {"name": "Krastorio2", "enabled": false},
+            {"name": "even-distribution"}
+        ]}"#;
+        let entries = parse_mod_list(json).unwrap();
+        assert_eq!(names(&entries), vec!["flib", "even-distribution"]);
+        assert_eq!(entries[0].version.as_deref(), Some("0.12.9"));
+    }
+
+    #[test]
+    fn parses_plain_list() {
+        let text = "flib 0.12.9\n# comment\n\nbase\nSqueak Through\nKrastorio2_1.3.24.zip, even-distribution";
+        let entries = parse_mod_list(text).unwrap();
+        assert_eq!(names(&entries), vec!["flib", "Squeak Through", "Krastorio2", "even-distribution"]);
+        assert_eq!(entries[0].version.as_deref(), Some("0.12.9"));
+        assert_eq!(entries[2].version.as_deref(), Some("1.3.24"));
+        assert_eq!(entries[1].version, None);
+    }
+
+    // Header in the layout of a Factorio 2.0 save, up to the mod list
+    fn save_header(mods: &[(&str, [u16; 3])]) -> Vec<u8> {
+        let string = |out: &mut Vec<u8>, s: &str| {
+            out.push(u8::try_from(s.len()).unwrap());
+            out.extend_from_slice(s.as_bytes());
+        };
+        let mut header = vec![2, 0, 0, 0, 28, 0, 0, 0, 0];
+        string(&mut header, "");
+        string(&mut header, "freeplay");
+        string(&mut header, "base");
+        header.extend_from_slice(&[1, 0, 0]);
+        string(&mut header, "");
+        header.extend_from_slice(&[1, 0, 0, 0, 0, 2, 0, 28, 0, 1, 2]);
+        header.push(u8::try_from(mods.len()).unwrap());
+        for (name, version) in mods {
+            string(&mut header, name);
+            for part in version {
+                match u8::try_from(*part) {
+                    Ok(b) if b < 0xFF => header.push(b),
+                    _ => {
+                        header.push(0xFF);
+                        header.extend_from_slice(&part.to_le_bytes());
+                    },
+                }
+            }
+            header.extend_from_slice(&[0xAB, 0xCD, 0xEF, 0x01]);
+        }
+        header.extend_from_slice(b"\x04base map data follows");
+        header
+    }
+
+    #[test]
+    fn reads_save_mod_list() {
+        use std::io::Write;
+        use flate2::{write::ZlibEncoder, Compression};
+
+        let header = save_header(&[("base", [2, 0, 28]), ("flib", [0, 16, 2]), ("Squeak Through", [1, 8, 300])]);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&header).unwrap();
+        let level_init = encoder.finish().unwrap();
+
+        let mut save = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        save.start_file("My save/control.lua", zip::write::FileOptions::default()).unwrap();
+        save.write_all(b"-- scenario").unwrap();
+        save.start_file("My save/level-init.dat", zip::write::FileOptions::default()).unwrap();
+        save.write_all(&level_init).unwrap();
+        let save = save.finish().unwrap().into_inner();
+
+        let entries = read_save_mod_list(&save).unwrap();
+        assert_eq!(names(&entries), vec!["base", "flib", "Squeak Through"]);
+        assert_eq!(entries[1].version.as_deref(), Some("0.16.2"));
+        assert_eq!(entries[2].version.as_deref(), Some("1.8.300"));
+
+        assert!(read_save_mod_list(b"not a zip").is_err());
+        assert!(parse_save_mods(&header[..header.len() / 2]).is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+use log::{error, info};
+use poise::serenity_prelude::{self as serenity, ButtonStyle, ComponentInteraction, CreateActionRow, CreateButton,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, UserId};
+use poise::CreateReply;
+use sqlx::{Pool, Sqlite};
+
+use crate::{Context, Error, custom_errors::CustomError};
+
+// Prefix of the custom ID of "Notify me" buttons, followed by the mod name
+pub const NOTIFY_BUTTON_PREFIX: &str = "notify_me:";
+
+/// "Notify me" button for an update message, if the mod name fits in a custom ID.
+pub fn make_notify_button(mod_name: &str) -> Option<CreateActionRow> {
+    let custom_id = format!("{NOTIFY_BUTTON_PREFIX}{mod_name}");
+    if custom_id.len() > 100 {
+        return None
+    }
+    let button = CreateButton::new(custom_id)
+        .label("Notify me")
+        .emoji('🔔')
+        .style(ButtonStyle::Secondary);
+    Some(CreateActionRow::Buttons(vec![button]))
+}
+
+/// Toggle the clicking user on the notify list of the mod the button belongs to.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn on_notify_button(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    db: &Pool<Sqlite>,
+) -> Result<(), Error> {
+    let Some(mod_name) = interaction.data.custom_id.strip_prefix(NOTIFY_BUTTON_PREFIX) else {
+        return Ok(())
+    };
+    let user_id = interaction.user.id.get() as i64;
+    let removed = sqlx::query!(r#"DELETE FROM mod_notify_users WHERE user_id = $1 AND mod_name = $2"#, user_id, mod_name)
+        .execute(db)
+        .await?
+        .rows_affected();
+    let response = if removed > 0 {
+        format!("You will no longer be notified about updates to {mod_name}")
+    } else {
+        sqlx::query!(r#"INSERT INTO mod_notify_users (user_id, mod_name) VALUES ($1, $2)"#, user_id, mod_name)
+            .execute(db)
+            .await?;
+        format!("You will receive a direct message when {mod_name} updates. Click again or use `/my_mod_pings remove` to stop.")
+    };
+    let message = CreateInteractionResponseMessage::new()
+        .content(response)
+        .ephemeral(true);
+    interaction.create_response(ctx, CreateInteractionResponse::Message(message)).await?;
+    Ok(())
+}
+
+/// Send `embed` as a direct message to everyone on the notify list of `mod_name`.
+#[allow(clippy::cast_sign_loss)]
+pub async fn notify_users(
+    mod_name: &str,
+    embed: CreateEmbed,
+    db: &Pool<Sqlite>,
+    cache_http: &Arc<serenity::Http>,
+) -> Result<(), Error> {
+    let users = sqlx::query!(r#"SELECT user_id FROM mod_notify_users WHERE mod_name = $1"#, mod_name)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|rec| UserId::new(rec.user_id as u64))
+        .collect::<Vec<UserId>>();
+    if users.is_empty() {
+        return Ok(())
+    }
+    info!("Notifying {} users about update to {mod_name}", users.len());
+    let http = cache_http.clone();
+    // DMs are sent one by one, don't hold up the update loop for them
+    tokio::spawn(async move {
+        for user in users {
+            let result = match user.create_dm_channel(&http).await {
+                Ok(channel) => channel.send_message(&http, CreateMessage::new().embed(embed.clone())).await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                error!("Error sending update notification to user {user}: {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Manage the mods you get a direct message about when they update
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, subcommands("list", "remove"), subcommand_required, category="Subscriptions")]
+pub async fn my_mod_pings(
+    _: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// List the mods you are notified about
+#[allow(clippy::cast_possible_wrap)]
+#[poise::command(prefix_command, slash_command, ephemeral)]
+pub async fn list(
+    ctx: Context<'_>
+) -> Result<(), Error> {
+    let user_id = ctx.author().id.get() as i64;
+    let mods = get_notify_mods(&ctx.data().database, user_id).await?;
+    let response = if mods.is_empty() {
+        "You are not notified about any mods. Click \"Notify me\" on an update message to start.".to_owned()
+    } else {
+        format!("**You are notified about:**\n{}", mods.join("\n"))
+    };
+    ctx.send(CreateReply::default().content(response)).await?;
+    Ok(())
+}
+
+/// Stop being notified about a mod
+#[allow(clippy::cast_possible_wrap)]
+#[poise::command(prefix_command, slash_command, ephemeral)]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Name of the mod"]
+    #[autocomplete = "autocomplete_notify_mod"]
+    modname: String,
+) -> Result<(), Error> {
+    let user_id = ctx.author().id.get() as i64;
+    let db = &ctx.data().database;
+    match sqlx::query!(r#"DELETE FROM mod_notify_users WHERE user_id = $1 AND mod_name = $2"#, user_id, modname)
+        .execute(db)
+        .await?
+        .rows_affected() {
+        0 => return Err(Box::new(CustomError::new(&format!("You are not notified about {modname}")))),
+        _ => ctx.say(format!("You will no longer be notified about updates to {modname}")).await?,
+    };
+    Ok(())
+}
+
+#[allow(clippy::cast_possible_wrap)]
+async fn autocomplete_notify_mod(
+    ctx: Context<'_>,
+    partial: &str,
+) -> Vec<String> {
+    let user_id = ctx.author().id.get() as i64;
+    get_notify_mods(&ctx.data().database, user_id).await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| name.to_lowercase().starts_with(&partial.to_lowercase()))
+        .collect()
+}
+
+async fn get_notify_mods(db: &Pool<Sqlite>, user_id: i64) -> Result<Vec<String>, Error> {
+    Ok(sqlx::query!(r#"SELECT mod_name FROM mod_notify_users WHERE user_id = $1 ORDER BY mod_name"#, user_id)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|rec| rec.mod_name)
+        .collect())
+}
@@ -0,0 +1,395 @@
+use std::{collections::{HashMap, HashSet, VecDeque}, sync::LazyLock};
+use futures::StreamExt;
+use log::{error, info};
+use poise::serenity_prelude::{Attachment, Colour, CreateEmbed};
+use poise::CreateReply;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{Context, Error, custom_errors::CustomError,
+    mod_list::{self, ModListEntry},
+    util::embed_list_field,
+};
+
+// Upper bound on mods fetched from the mod portal for a single check, including suggested dependencies
+const MAX_FETCHED_MODS: usize = 150;
+const CONCURRENT_FETCHES: usize = 8;
+
+static DEPENDENCY_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(!|\?|\(\?\)|~)?\s*(.+?)\s*(?:(<=|>=|<|>|=)\s*(\d+(?:\.\d+)*))?\s*$").unwrap()
+});
+
+#[derive(Deserialize, Debug)]
+struct FullMod {
+    releases: Vec<FullRelease>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FullRelease {
+    version: String,
+    info_json: FullInfoJson,
+}
+
+#[derive(Deserialize, Debug)]
+struct FullInfoJson {
+    factorio_version: String,
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyKind {
+    Required,
+    Optional,
+    HiddenOptional,
+    Incompatible,
+    NoLoadOrder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionOp {
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+}
+
+impl VersionOp {
+    fn matches(self, version: &[u32], constraint: &[u32]) -> bool {
+        match self {
+            Self::Less => version < constraint,
+            Self::LessEqual => version <= constraint,
+            Self::Equal => version == constraint,
+            Self::GreaterEqual => version >= constraint,
+            Self::Greater => version > constraint,
+        }
+    }
+
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Equal => "=",
+            Self::GreaterEqual => ">=",
+            Self::Greater => ">",
+        }
+    }
+}
+
+/// A single entry of the `dependencies` list in a mod's info.json
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub kind: DependencyKind,
+    pub name: String,
+    pub constraint: Option<(VersionOp, String)>,
+}
+
+impl Dependency {
+    /// Parse a dependency string like `? some-mod >= 1.2.0`.
+    pub fn parse(dependency: &str) -> Option<Self> {
+        let caps = DEPENDENCY_REGEX.captures(dependency)?;
+        let kind = match caps.get(1).map(|m| m.as_str()) {
+            None => DependencyKind::Required,
+            Some("?") => DependencyKind::Optional,
+            Some("(?)") => DependencyKind::HiddenOptional,
+            Some("!") => DependencyKind::Incompatible,
+            Some(_) => DependencyKind::NoLoadOrder,
+        };
+        let op = caps.get(3).map(|m| match m.as_str() {
+            "<" => VersionOp::Less,
+            "<=" => VersionOp::LessEqual,
+            ">=" => VersionOp::GreaterEqual,
+            ">" => VersionOp::Greater,
+            _ => VersionOp::Equal,
+        });
+        Some(Self {
+            kind,
+            name: caps[2].to_owned(),
+            constraint: op.zip(caps.get(4).map(|m| m.as_str().to_owned())),
+        })
+    }
+
+    const fn is_required(&self) -> bool {
+        matches!(self.kind, DependencyKind::Required | DependencyKind::NoLoadOrder)
+    }
+}
+
+/// Release of a mod that is checked: the listed version if known, the latest otherwise
+#[derive(Debug, Clone)]
+pub struct ModRelease {
+    pub version: String,
+    pub factorio_version: String,
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Default)]
+pub struct ModpackReport {
+    pub incompatible: Vec<String>,
+    pub version_conflicts: Vec<String>,
+    pub factorio_mismatch: Vec<String>,
+    pub missing: Vec<String>,
+    pub unknown: Vec<String>,
+}
+
+impl ModpackReport {
+    pub const fn is_ok(&self) -> bool {
+        self.incompatible.is_empty() && self.version_conflicts.is_empty() && self.factorio_mismatch.is_empty()
+            && self.missing.is_empty() && self.unknown.is_empty()
+    }
+}
+
+/// Check the listed mods against each other. `releases` holds the listed mods and any known
+/// missing dependencies, so that dependencies of missing mods can be suggested as well.
+pub fn check_modpack(
+    listed: &[ModListEntry],
+    releases: &HashMap<String, ModRelease>,
+    factorio_version: &str,
+) -> ModpackReport {
+    let mut report = ModpackReport::default();
+    let listed_names = listed.iter().map(|m| m.name.as_str()).collect::<HashSet<&str>>();
+    let mut reported_pairs = HashSet::new();
+    for entry in listed {
+        let Some(release) = releases.get(&entry.name) else {
+            report.unknown.push(entry.name.clone());
+            continue;
+        };
+        if release.factorio_version != factorio_version {
+            report.factorio_mismatch.push(format!("{} {} (Factorio {})", entry.name, release.version, release.factorio_version));
+        }
+        for dependency in &release.dependencies {
+            if mod_list::is_builtin(&dependency.name) {
+                continue;
+            }
+            let present = listed_names.contains(dependency.name.as_str());
+            if dependency.kind == DependencyKind::Incompatible {
+                // Both mods usually declare the incompatibility, only report it once
+                let mut pair = [entry.name.clone(), dependency.name.clone()];
+                pair.sort();
+                if present && reported_pairs.insert(pair) {
+                    report.incompatible.push(format!("{} and {}", entry.name, dependency.name));
+                }
+                continue;
+            }
+            if !present {
+                continue;
+            }
+            let Some((op, constraint)) = &dependency.constraint else {
+                continue;
+            };
+            let Some(present_version) = releases.get(&dependency.name).map(|r| r.version.as_str()) else {
+                continue;
+            };
+            let satisfied = mod_list::parse_version(present_version)
+                .zip(mod_list::parse_version(constraint))
+                .is_none_or(|(version, constraint)| op.matches(&version, &constraint));
+            if !satisfied {
+                report.version_conflicts.push(format!("{} needs {} {} {constraint}, found {present_version}",
+                    entry.name, dependency.name, op.as_str()));
+            }
+        }
+    }
+    report.missing = missing_dependencies(listed, releases);
+    report
+}
+
+// Required dependencies not in the list, followed through the dependencies of the missing mods themselves
+fn missing_dependencies(listed: &[ModListEntry], releases: &HashMap<String, ModRelease>) -> Vec<String> {
+    let mut known = listed.iter().map(|m| m.name.clone()).collect::<HashSet<String>>();
+    let mut queue = listed.iter().map(|m| m.name.clone()).collect::<VecDeque<String>>();
+    let mut missing = Vec::new();
+    while let Some(name) = queue.pop_front() {
+        let Some(release) = releases.get(&name) else {
+            continue;
+        };
+        for dependency in release.dependencies.iter().filter(|d| d.is_required()) {
+            if mod_list::is_builtin(&dependency.name) || !known.insert(dependency.name.clone()) {
+                continue;
+            }
+            missing.push(format!("{} (needed by {name})", dependency.name));
+            queue.push_back(dependency.name.clone());
+        }
+    }
+    missing
+}
+
+// Release of a mod from the mod portal, `None` if the mod does not exist
+async fn fetch_release(client: &reqwest::Client, name: &str, version: Option<&str>) -> Result<Option<ModRelease>, Error> {
+    let url = format!("https://mods.factorio.com/api/mods/{name}/full");
+    let response = client.get(url).send().await?;
+    match response.status() {
+        reqwest::StatusCode::OK => (),
+        reqwest::StatusCode::NOT_FOUND => return Ok(None),
+        _ => return Err(Box::new(CustomError::new(&format!("Received HTTP status code {} while accessing mod portal API", response.status().as_str())))),
+    }
+    let mod_info = response.json::<FullMod>().await?;
+    let release = version
+        .and_then(|v| mod_info.releases.iter().find(|r| r.version == v))
+        .or_else(|| mod_info.releases.last());
+    Ok(release.map(|r| ModRelease {
+        version: r.version.clone(),
+        factorio_version: r.info_json.factorio_version.clone(),
+        dependencies: r.info_json.dependencies.iter().filter_map(|d| Dependency::parse(d)).collect(),
+    }))
+}
+
+// Fetch the listed mods and, level by level, the required dependencies missing from the list.
+// Mods that could not be fetched are returned with the error, so one failure does not stop the whole check.
+async fn fetch_releases(listed: &[ModListEntry]) -> (HashMap<String, ModRelease>, Vec<(String, String)>) {
+    let client = reqwest::Client::new();
+    let mut releases = HashMap::new();
+    let mut failed = Vec::new();
+    let mut requested = listed.iter().map(|m| m.name.clone()).collect::<HashSet<String>>();
+    let mut to_fetch = listed.iter()
+        .map(|m| (m.name.clone(), m.version.clone()))
+        .collect::<Vec<(String, Option<String>)>>();
+    while !to_fetch.is_empty() && releases.len() < MAX_FETCHED_MODS {
+        let fetched = futures::stream::iter(to_fetch)
+            .map(|(name, version)| {
+                let client = &client;
+                async move {
+                    let release = fetch_release(client, &name, version.as_deref()).await;
+                    (name, release)
+                }
+            })
+            .buffer_unordered(CONCURRENT_FETCHES)
+            .collect::<Vec<(String, Result<Option<ModRelease>, Error>)>>()
+            .await;
+        to_fetch = Vec::new();
+        for (name, release) in fetched {
+            let release = match release {
+                Ok(Some(release)) => release,
+                Ok(None) => continue,
+                Err(e) => {
+                    error!("Error fetching mod {name} for modpack check: {e}");
+                    failed.push((name, e.to_string()));
+                    continue;
+                },
+            };
+            for dependency in release.dependencies.iter().filter(|d| d.is_required()) {
+                if !mod_list::is_builtin(&dependency.name) && requested.insert(dependency.name.clone()) {
+                    to_fetch.push((dependency.name.clone(), None));
+                }
+            }
+            releases.insert(name, release);
+        }
+    }
+    (releases, failed)
+}
+
+/// Check modpacks for problems that keep them from loading
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, subcommands("check"), subcommand_required)]
+pub async fn modpack(
+    _: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Check a list of mods for missing dependencies, incompatibilities and version conflicts
+#[poise::command(prefix_command, slash_command)]
+pub async fn check(
+    ctx: Context<'_>,
+    #[description = "Comma separated mod names"]
+    mods: Option<String>,
+    #[description = "mod-list.json, a save file, or a text file with one mod per line"]
+    file: Option<Attachment>,
+    #[description = "Factorio version the modpack is for, latest used by the mods by default"]
+    factorio_version: Option<String>,
+) -> Result<(), Error> {
+    // Save files can take a while to download
+    ctx.defer().await?;
+    let listed = match (&file, mods) {
+        (Some(file), _) => mod_list::download_mod_list(file).await?,
+        (None, Some(mods)) => mod_list::parse_mod_list(&mods)?,
+        (None, None) => return Err(Box::new(CustomError::new("Provide a list of mods, a mod-list.json or a save file"))),
+    };
+    if listed.is_empty() {
+        return Err(Box::new(CustomError::new("No enabled mods found in this list")))
+    }
+    info!("Checking modpack of {} mods", listed.len());
+    let (releases, failed) = fetch_releases(&listed).await;
+    let factorio_version = factorio_version.unwrap_or_else(|| {
+        listed.iter()
+            .filter_map(|m| releases.get(&m.name))
+            .map(|r| r.factorio_version.clone())
+            .max_by_key(|v| mod_list::parse_version(v).unwrap_or_default())
+            .unwrap_or_default()
+    });
+    let mut report = check_modpack(&listed, &releases, &factorio_version);
+    // Mods that could not be fetched may still be on the mod portal
+    report.unknown.retain(|name| failed.iter().all(|(failed_name, _)| failed_name != name));
+    let failed = failed.into_iter()
+        .map(|(name, e)| format!("{name}: {e}"))
+        .collect::<Vec<String>>();
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("Modpack check: {} mods for Factorio {factorio_version}", listed.len()));
+    if report.is_ok() && failed.is_empty() {
+        embed = embed.description("No problems found").color(Colour::DARK_GREEN);
+    } else {
+        embed = embed.color(Colour::ORANGE);
+    }
+    for (title, items) in [
+        ("Incompatible mods", &report.incompatible),
+        ("Version conflicts", &report.version_conflicts),
+        ("Wrong Factorio version", &report.factorio_mismatch),
+        ("Missing dependencies", &report.missing),
+        ("Not found on the mod portal", &report.unknown),
+        ("Could not be checked", &failed),
+    ] {
+        if !items.is_empty() {
+            embed = embed.field(format!("{title} ({})", items.len()), embed_list_field(items), false);
+        }
+    }
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn entry(name: &str) -> ModListEntry {
+        ModListEntry { name: name.to_owned(), enabled: true, version: None }
+    }
+
+    fn release(version: &str, dependencies: &[&str]) -> ModRelease {
+        ModRelease {
+            version: version.to_owned(),
+            factorio_version: "2.0".to_owned(),
+            dependencies: dependencies.iter().filter_map(|d| Dependency::parse(d)).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_dependencies() {
+        assert_eq!(Dependency::parse("base >= 2.0.0"), Some(Dependency {
+            kind: DependencyKind::Required,
+            name: "base".to_owned(),
+            constraint: Some((VersionOp::GreaterEqual, "2.0.0".to_owned())),
+        }));
+        assert_eq!(Dependency::parse("? Squeak Through").unwrap().name, "Squeak Through");
+        assert_eq!(Dependency::parse("(?) flib").unwrap().kind, DependencyKind::HiddenOptional);
+        assert_eq!(Dependency::parse("!bobplates").unwrap().kind, DependencyKind::Incompatible);
+        assert_eq!(Dependency::parse("~ flib<0.13").unwrap().constraint, Some((VersionOp::Less, "0.13".to_owned())));
+    }
+
+    #[test]
+    fn reports_modpack_problems() {
+        let listed = vec![entry("a"), entry("b"), entry("c")];
+        let releases = HashMap::from([
+            ("a".to_owned(), release("1.0.0", &["base >= 2.0", "flib >= 0.15", "! b"])),
+            ("b".to_owned(), release("1.0.0", &["! a", "c >= 2.0.0"])),
+            ("c".to_owned(), release("1.5.0", &["? a"])),
+            ("flib".to_owned(), release("0.15.0", &["stdlib"])),
+        ]);
+        let report = check_modpack(&listed, &releases, "2.0");
+        assert_eq!(report.incompatible, vec!["a and b"]);
+        assert_eq!(report.version_conflicts, vec!["b needs c >= 2.0.0, found 1.5.0"]);
+        assert_eq!(report.missing, vec!["flib (needed by a)", "stdlib (needed by flib)"]);
+        assert!(report.factorio_mismatch.is_empty());
+        assert!(report.unknown.is_empty());
+    }
+}
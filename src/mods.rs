@@ -1,12 +1,16 @@
 use serde::{Deserialize, Serialize};
-use serenity::all::{Colour, CreateEmbed, CreateMessage};
+use futures::StreamExt;
+use serenity::all::{Builder, ChannelId, CreateActionRow, CreateAllowedMentions, CreateEmbed, CreateMessage, CreateWebhook, ExecuteWebhook, RoleId};
 use sqlx::{Pool, Sqlite};
-use std::{fmt, sync::{Arc, RwLock}};
+use std::{collections::HashMap, fmt, sync::{Arc, RwLock}};
 use log::{error, info};
 
 use crate::Error;
 use crate::custom_errors::CustomError;
-use crate::util::{escape_formatting, get_subscribed_mods, get_subscribed_authors};
+use crate::util::escape_formatting;
+use crate::outgoing_webhooks::{deliver_all, get_outgoing_webhooks, ModUpdatePayload, OutgoingWebhook};
+use crate::notification_style::{get_notification_styles, NotificationStyle};
+use crate::mod_notify::{make_notify_button, notify_users};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ApiResponse {
@@ -42,6 +46,8 @@ pub struct Mod {
     pub category: Option<Category>,
     pub thumbnail: Option<String>,
     pub changelog: Option<String>,
+    #[serde(default)]
+    pub deprecated: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -88,11 +94,22 @@ impl fmt::Display for Category {
     }
 }
 
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
 pub enum ModState{
     Updated,
     New,
 }
 
+impl ModState {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Updated => "updated",
+            Self::New => "new",
+        }
+    }
+}
+
 #[allow(clippy::module_name_repetitions)]
 pub async fn get_mods(page: i32, initializing: bool) -> Result<ApiResponse, Error> {
 
@@ -115,6 +132,7 @@ pub async fn update_database(
     ) -> Result<(), Error> {
     let mut page = 1;
     let mut old_mod_encountered = false;
+    let mut targets: Option<Vec<Server>> = None;   // Loaded when the first update needs to be sent
     while !old_mod_encountered {
         let mods = get_mods(page, initializing).await?;
         page += 1;
@@ -144,8 +162,8 @@ pub async fn update_database(
             };
             
             sqlx::query!(r#"INSERT OR REPLACE INTO mods 
-                    (name, title, owner, summary, category, downloads_count, factorio_version, version, released_at)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"#, 
+                    (name, title, owner, summary, category, downloads_count, factorio_version, version, released_at, deprecated)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"#, 
                     result.name,
                     result.title,
                     result.owner,
@@ -154,13 +172,14 @@ pub async fn update_database(
                     result.downloads_count,
                     factorio_version,
                     version,
-                    timestamp)
+                    timestamp,
+                    result.deprecated)
                     .execute(&db)
                     .await?;
             
             if !initializing {  // Only send messages when not initializing database
                 let thumbnail = get_mod_thumbnail(&result.name).await?;
-                let changelog = get_mod_changelog(&result.name, Some(15)).await?;
+                let changelog = get_mod_changelog(&result.name, None).await?;
                 let updated_mod = UpdatedMod{
                     name: result.name,
                     title: result.title,
@@ -168,114 +187,369 @@ pub async fn update_database(
                     version,
                     thumbnail,
                     changelog,
+                    released_at: timestamp,
                     state
                 };
-                send_mod_update(updated_mod, db.clone(), cache_http).await?;
+                log_mod_update(&db, &updated_mod).await?;
+                if targets.is_none() {
+                    targets = Some(get_update_targets(&db).await?);
+                }
+                send_mod_update(updated_mod, targets.as_deref().unwrap_or_default(), &db, cache_http).await;
             }
         };
         if initializing {
             break;  // Break after first loop as it retrieves all mods at once when initializing.
         }
     }
+    if !initializing {
+        let cutoff = chrono::Utc::now().timestamp() - UPDATE_LOG_RETENTION_DAYS * 24 * 60 * 60;
+        sqlx::query!(r#"DELETE FROM mod_update_log WHERE released_at < $1"#, cutoff)
+            .execute(&db)
+            .await?;
+    }
     info!("Database updated!");
     Ok(())
 }
 
-struct UpdatedMod{
-    name: String,
-    title: String,
-    author: String,
-    version: String,
-    thumbnail: String,
-    changelog: String,
-    state: ModState,
+// How long mod updates are kept in `mod_update_log`
+const UPDATE_LOG_RETENTION_DAYS: i64 = 30;
+
+async fn log_mod_update(db: &Pool<Sqlite>, updated_mod: &UpdatedMod) -> Result<(), Error> {
+    let state = updated_mod.state.as_str();
+    sqlx::query!(r#"INSERT INTO mod_update_log (name, title, author, version, state, changelog, released_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)"#,
+        updated_mod.name,
+        updated_mod.title,
+        updated_mod.author,
+        updated_mod.version,
+        state,
+        updated_mod.changelog,
+        updated_mod.released_at)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+pub struct UpdatedMod{
+    pub name: String,
+    pub title: String,
+    pub author: String,
+    pub version: String,
+    pub thumbnail: String,
+    pub changelog: String,
+    pub released_at: i64,
+    pub state: ModState,
+}
+
+impl UpdatedMod {
+    fn to_payload(&self) -> ModUpdatePayload {
+        ModUpdatePayload {
+            name: self.name.clone(),
+            title: self.title.clone(),
+            author: self.author.clone(),
+            version: self.version.clone(),
+            state: self.state,
+            changelog: self.changelog.clone(),
+            timestamp: self.released_at,
+        }
+    }
+}
+
+// Upper bound on update messages in flight at once. Serenity's ratelimiter
+// queues requests per route, this only keeps a single update from flooding it.
+const MAX_CONCURRENT_SENDS: usize = 10;
+
+// Longest description Discord accepts in an embed
+pub const EMBED_DESCRIPTION_LIMIT: usize = 4096;
+
+struct UpdateWebhook {
+    url: String,
+    name: Option<String>,
+    avatar_url: Option<String>,
 }
 
 struct Server {
     id: i64,
-    updates_channel: Option<i64>,
+    updates_channel: Option<ChannelId>,
     show_changelog: bool,
+    style: NotificationStyle,
+    webhook: Option<UpdateWebhook>,
+    outgoing_webhooks: Vec<OutgoingWebhook>,
+    // Subscriptions with the role to ping for them, if any
+    subscribed_mods: HashMap<String, Option<RoleId>>,
+    subscribed_authors: HashMap<String, Option<RoleId>>,
+}
+
+impl Server {
+    fn wants_update(&self, updated_mod: &UpdatedMod) -> bool {
+        (self.subscribed_mods.is_empty() && self.subscribed_authors.is_empty()) ||  // No subscriptions
+            self.subscribed_mods.contains_key(&updated_mod.name) ||     // Subscribed to mod
+            self.subscribed_authors.contains_key(&updated_mod.author)   // Subscribed to author
+    }
+
+    // Explicitly subscribed, as opposed to receiving all updates
+    fn is_subscribed(&self, updated_mod: &UpdatedMod) -> bool {
+        self.subscribed_mods.contains_key(&updated_mod.name) || self.subscribed_authors.contains_key(&updated_mod.author)
+    }
+
+    // Roles to mention for an update: the server wide ping role and any roles attached to matching subscriptions
+    fn ping_roles(&self, updated_mod: &UpdatedMod) -> Vec<RoleId> {
+        if !self.is_subscribed(updated_mod) {
+            return Vec::new();
+        }
+        let mut roles = [
+            self.style.ping_role,
+            self.subscribed_mods.get(&updated_mod.name).copied().flatten(),
+            self.subscribed_authors.get(&updated_mod.author).copied().flatten(),
+        ].into_iter().flatten().collect::<Vec<RoleId>>();
+        roles.sort_unstable();
+        roles.dedup();
+        roles
+    }
 }
 
+/// Load every server with an updates channel or outgoing webhook together with its subscriptions.
 #[allow(clippy::cast_sign_loss)]
-async fn send_mod_update(
-        updated_mod: UpdatedMod, 
-        db: Pool<Sqlite>, 
-        cache_http: &Arc<poise::serenity_prelude::Http>
-    ) -> Result<(), Error> {
-    info!("Sending mod update message for {}", updated_mod.title);
-    let server_data = sqlx::query!(r#"SELECT * FROM servers"#)
-        .fetch_all(&db)
+async fn get_update_targets(db: &Pool<Sqlite>) -> Result<Vec<Server>, Error> {
+    let mut styles = get_notification_styles(db).await?;
+    let mut servers = sqlx::query!(r#"
+        SELECT server_id, updates_channel, show_changelog, webhook_url, webhook_name, webhook_avatar FROM servers"#)
+        .fetch_all(db)
         .await?
         .into_iter()
-        .map(|s| { 
-            Ok(Server{
+        .map(|s| {
+            (s.server_id, Server{
                 id: s.server_id,
-                updates_channel: s.updates_channel,
+                updates_channel: s.updates_channel.map(|ch| ChannelId::new(ch as u64)),
                 show_changelog: s.show_changelog.unwrap_or(true),
+                style: styles.remove(&s.server_id).unwrap_or_default(),
+                webhook: s.webhook_url.map(|url| UpdateWebhook{
+                    url,
+                    name: s.webhook_name,
+                    avatar_url: s.webhook_avatar,
+                }),
+                outgoing_webhooks: Vec::new(),
+                subscribed_mods: HashMap::new(),
+                subscribed_authors: HashMap::new(),
             })
         })
-        .collect::<Vec<Result<Server, Error>>>();
-    for server_res in server_data {
-        let server = match server_res {
-            Ok(s) => s,
-            Err(e) => {
-                error!{"Error sending update message: {e}"};
-                continue;
-            },
-        };
-        let subscribed_mods = get_subscribed_mods(&db, server.id).await?;
-        let subscribed_authors = get_subscribed_authors(&db, server.id).await?;
+        .collect::<HashMap<i64, Server>>();
+
+    for (server_id, webhook) in get_outgoing_webhooks(db).await? {
+        servers.entry(server_id)
+            .or_insert_with(|| Server{
+                id: server_id,
+                updates_channel: None,
+                show_changelog: true,
+                style: NotificationStyle::default(),
+                webhook: None,
+                outgoing_webhooks: Vec::new(),
+                subscribed_mods: HashMap::new(),
+                subscribed_authors: HashMap::new(),
+            })
+            .outgoing_webhooks.push(webhook);
+    }
 
-        
-        let updates_channel: poise::serenity_prelude::ChannelId = match server.updates_channel {
-            Some(ch) => poise::serenity_prelude::ChannelId::new(ch as u64),
-            None => continue,
+    let subscriptions = sqlx::query!(r#"
+        SELECT server_id AS "server_id!", mod_name AS "name!", ping_role, FALSE AS "is_author!: bool" FROM subscribed_mods
+        UNION ALL
+        SELECT server_id AS "server_id!", author_name AS "name!", ping_role, TRUE AS "is_author!: bool" FROM subscribed_authors
+            WHERE server_id IS NOT NULL AND author_name IS NOT NULL"#)
+        .fetch_all(db)
+        .await?;
+    for sub in subscriptions {
+        let Some(server) = servers.get_mut(&sub.server_id) else {
+            continue;
         };
+        let ping_role = sub.ping_role.map(|r| RoleId::new(r as u64));
+        if sub.is_author {
+            server.subscribed_authors.insert(sub.name, ping_role);
+        } else {
+            server.subscribed_mods.insert(sub.name, ping_role);
+        }
+    }
+    Ok(servers.into_values()
+        .filter(|s| s.updates_channel.is_some() || !s.outgoing_webhooks.is_empty())
+        .collect())
+}
+
+async fn send_mod_update(
+        updated_mod: UpdatedMod, 
+        targets: &[Server], 
+        db: &Pool<Sqlite>,
+        cache_http: &Arc<poise::serenity_prelude::Http>
+    ) {
+    info!("Sending mod update message for {}", updated_mod.title);
+    let interested = targets.iter()
+        .filter(|server| server.wants_update(&updated_mod))
+        .collect::<Vec<&Server>>();
+    let outgoing = interested.iter()
+        .flat_map(|server| server.outgoing_webhooks.clone())
+        .collect::<Vec<OutgoingWebhook>>();
+    // Outgoing webhooks retry on their own schedule, don't hold up the Discord messages for them
+    if !outgoing.is_empty() {
+        tokio::spawn(deliver_all(outgoing, updated_mod.to_payload()));
+    }
+    futures::stream::iter(interested)
+        .for_each_concurrent(MAX_CONCURRENT_SENDS, |server| {
+            make_update_message(&updated_mod, server, db, cache_http)
+        })
+        .await;
+
+    let embed = make_update_embed(&updated_mod, &NotificationStyle::default(), true).await;
+    if let Err(e) = notify_users(&updated_mod.name, embed, db, cache_http).await {
+        error!("Error notifying users about mod update: {e}");
+    }
+}
+
+/// Build the update embed for `updated_mod` in the given style.
+pub async fn make_update_embed(
+        updated_mod: &UpdatedMod,
+        style: &NotificationStyle,
+        show_changelog: bool
+    ) -> CreateEmbed {
+    let url = format!("https://mods.factorio.com/mod/{}", updated_mod.name);
+    let title = style.format_title(
+        updated_mod.state,
+        &escape_formatting(&updated_mod.title).await,
+        &escape_formatting(&updated_mod.name).await,
+        &escape_formatting(&updated_mod.author).await,
+        &updated_mod.version,
+    );
+    let changelog = if show_changelog { trim_changelog(&updated_mod.changelog, style.changelog_lines) } else { String::new() };
+    let mut embed = CreateEmbed::new()
+        .title(title)
+        .url(url)
+        .color(style.color(updated_mod.state))
+        .description(changelog);
+    if style.show_author {
+        let author_link = format!("{} ([more](https://mods.factorio.com/user/{}))", escape_formatting(&updated_mod.author).await, &updated_mod.author);
+        embed = embed.field("**Author**", author_link, true);
+    }
+    if style.show_version {
+        embed = embed.field("**Version**", &updated_mod.version, true);
+    }
+    if style.show_thumbnail {
+        embed = embed.thumbnail(&updated_mod.thumbnail);
+    }
+    embed
+}
 
-        if (subscribed_mods.is_empty() && subscribed_authors.is_empty()) || // No subscriptions
-            subscribed_mods.contains(&updated_mod.name) ||      // Subscribed to mod
-            subscribed_authors.contains(&updated_mod.author)    // Subscribed to author
-        {
-            make_update_message(&updated_mod, updates_channel, server.show_changelog, cache_http).await?;
+/// Keep the first `lines` lines of a changelog, marking it if anything was cut off.
+/// The result always fits in an embed description.
+pub fn trim_changelog(changelog: &str, lines: usize) -> String {
+    const MARKER: &str = "\n<Trimmed>";
+    let mut out = changelog.lines().take(lines).collect::<Vec<&str>>().join("\n");
+    let too_long = out.chars().count() > EMBED_DESCRIPTION_LIMIT;
+    if too_long || changelog.lines().count() > lines {
+        if let Some((cut, _)) = out.char_indices().nth(EMBED_DESCRIPTION_LIMIT - MARKER.len()) {
+            out.truncate(cut);
         }
+        out.push_str(MARKER);
     }
-    Ok(())
+    out
 }
 
 async fn make_update_message(
         updated_mod: &UpdatedMod, 
-        updates_channel: serenity::model::prelude::ChannelId,
-        show_changelog: bool,
+        server: &Server,
+        db: &Pool<Sqlite>,
         cache_http: &Arc<serenity::all::Http>
-    ) -> Result<(), Error> {
-    let mut url = String::new();
-    url.push_str("https://mods.factorio.com/mod/");
-    url.push_str(&updated_mod.name);
-    let color = match updated_mod.state {
-        ModState::Updated => Colour::from_rgb(0x58, 0x65, 0xF2),
-        ModState::New => Colour::from_rgb(0x2E, 0xCC, 0x71),
-    };
-    let mut title = match updated_mod.state {
-        ModState::Updated => format!("Updated mod:\n{}", escape_formatting(&updated_mod.title).await),
-        ModState::New => format!("New mod:\n{}", escape_formatting(&updated_mod.title).await),
+    ) {
+    let embed = make_update_embed(updated_mod, &server.style, server.show_changelog).await;
+    let ping_roles = server.ping_roles(updated_mod);
+    let content = (!ping_roles.is_empty()).then(|| ping_roles.iter()
+        .map(|role| format!("<@&{role}>"))
+        .collect::<Vec<String>>()
+        .join(" "));
+    let mentions = CreateAllowedMentions::new().roles(ping_roles);
+    let components = make_notify_button(&updated_mod.name).into_iter().collect::<Vec<CreateActionRow>>();
+
+    // Fall back to a regular message if the webhook can't be used
+    if let Some(webhook) = &server.webhook {
+        match send_webhook_message(webhook, embed.clone(), content.clone(), mentions.clone(), components.clone(), cache_http).await {
+            Ok(true) => return,
+            Ok(false) => {
+                info!("Update webhook of server {} no longer exists, sending as regular message", server.id);
+                if let Err(e) = clear_update_webhook(db, server.id).await {
+                    error!("Error removing deleted webhook: {e}");
+                }
+            },
+            Err(e) => error!("Error sending message through webhook: {e}"),
+        }
+    }
+    let Some(updates_channel) = server.updates_channel else {
+        return
     };
-    title.truncate(256);
-    let changelog = if show_changelog { updated_mod.changelog.clone() } else { String::new() };
-    let author_link = format!("{} ([more](https://mods.factorio.com/user/{}))", escape_formatting(&updated_mod.author).await, &updated_mod.author);
-    let embed = CreateEmbed::new()
-        .title(&title)
-        .url(url)
-        .color(color)
-        .description(changelog)
-        .field("**Author**", &author_link, true)
-        .field("**Version**", &updated_mod.version, true)
-        .thumbnail(&updated_mod.thumbnail);
-    let builder = CreateMessage::new().embed(embed);
+    let mut builder = CreateMessage::new().embed(embed).allowed_mentions(mentions).components(components);
+    if let Some(content) = content {
+        builder = builder.content(content);
+    }
     match updates_channel.send_message(cache_http, builder).await {
         Ok(_) => {},
         Err(e) => error!("Error sending message: {e}"),
     };
+}
+
+/// Send an embed through an update webhook. Returns `false` if the webhook was deleted.
+async fn send_webhook_message(
+        webhook: &UpdateWebhook,
+        embed: CreateEmbed,
+        content: Option<String>,
+        mentions: CreateAllowedMentions,
+        components: Vec<CreateActionRow>,
+        cache_http: &Arc<serenity::all::Http>
+    ) -> Result<bool, Error> {
+    let url = reqwest::Url::parse(&webhook.url)?;
+    let Some((webhook_id, token)) = serenity::utils::parse_webhook(&url) else {
+        return Ok(false)
+    };
+    let mut builder = ExecuteWebhook::new().embed(embed).allowed_mentions(mentions).components(components);
+    if let Some(content) = content {
+        builder = builder.content(content);
+    }
+    if let Some(name) = &webhook.name {
+        builder = builder.username(name);
+    }
+    if let Some(avatar_url) = &webhook.avatar_url {
+        builder = builder.avatar_url(avatar_url);
+    }
+    match builder.execute(cache_http, (webhook_id, token, false)).await {
+        Ok(_) => Ok(true),
+        Err(serenity::Error::Http(e)) if e.status_code() == Some(reqwest::StatusCode::NOT_FOUND) => Ok(false),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+/// Create a webhook for mod updates in `channel`, returning its URL.
+pub async fn create_update_webhook(
+        channel: ChannelId,
+        cache_http: &Arc<serenity::all::Http>
+    ) -> Result<String, Error> {
+    let webhook = channel.create_webhook(cache_http, CreateWebhook::new("Mod updates")).await?;
+    Ok(webhook.url()?)
+}
+
+/// Delete a webhook created by `create_update_webhook`. Failures are only logged, as the webhook may already be gone.
+pub async fn delete_update_webhook(
+        url: &str,
+        cache_http: &Arc<serenity::all::Http>
+    ) {
+    let Ok(parsed_url) = reqwest::Url::parse(url) else {
+        return
+    };
+    let Some((webhook_id, token)) = serenity::utils::parse_webhook(&parsed_url) else {
+        return
+    };
+    if let Err(e) = cache_http.delete_webhook_with_token(webhook_id, token, None).await {
+        info!("Could not delete update webhook: {e}");
+    }
+}
+
+pub async fn clear_update_webhook(db: &Pool<Sqlite>, server_id: i64) -> Result<(), Error> {
+    sqlx::query!(r#"UPDATE servers SET webhook_url = NULL, webhook_name = NULL, webhook_avatar = NULL WHERE server_id = $1"#, server_id)
+        .execute(db)
+        .await?;
     Ok(())
 }
 
@@ -328,13 +602,36 @@ pub async fn get_mod_changelog(name: &String, lines: Option<i32>) -> Result<Stri
                     break;
                 }
             };
-            out.truncate(4096);
+            out.truncate(out.floor_char_boundary(4096));
             Ok(out)
         },
         None => Ok(String::new()),
     }
 }
 
+/// Refresh the deprecation flag of all known mods from the full mod list.
+/// `update_database` only sees mods with a new release, while mods are usually deprecated without one.
+pub async fn update_deprecated_flags(db: &Pool<Sqlite>) -> Result<u64, Error> {
+    let mods = get_mods(1, true).await?.results;
+    if mods.is_empty() {
+        return Err(Box::new(CustomError::new("Mod portal returned an empty mod list")))
+    }
+    set_deprecated_flags(db, mods.iter().map(|m| (m.name.as_str(), m.deprecated))).await
+}
+
+async fn set_deprecated_flags<'a>(db: &Pool<Sqlite>, flags: impl Iterator<Item = (&'a str, bool)>) -> Result<u64, Error> {
+    let mut transaction = db.begin().await?;
+    let mut changed = 0;
+    for (name, deprecated) in flags {
+        changed += sqlx::query!(r#"UPDATE mods SET deprecated = $1 WHERE name = $2 AND deprecated != $1"#, deprecated, name)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected();
+    }
+    transaction.commit().await?;
+    Ok(changed)
+}
+
 #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
 pub async fn get_mod_count(db: Pool<Sqlite>) -> i32 {
     let record = sqlx::query!(r#"SELECT name FROM mods"#)
@@ -351,18 +648,6 @@ pub struct ModCacheEntry {
     pub downloads_count: i64
 }
 
-#[derive(Debug, Clone)]
-pub enum SubscriptionType {
-    Author(String),
-    Modname(String),
-}
-
-#[derive(Debug, Clone)]
-pub struct SubCacheEntry{
-    pub server_id: i64,
-    pub subscription: SubscriptionType 
-}
-
 pub async fn update_mod_cache(
     cache: Arc<RwLock<Vec<ModCacheEntry>>>, 
     db: Pool<Sqlite>
@@ -389,44 +674,6 @@ pub async fn update_mod_cache(
     Ok(())
 }
 
-pub async fn update_sub_cache(
-    cache: Arc<RwLock<Vec<SubCacheEntry>>>,
-    db: Pool<Sqlite>
-) -> Result<(), Error> {
-    let mod_records = sqlx::query!(r#"SELECT * FROM subscribed_mods"#)
-        .fetch_all(&db)
-        .await?
-        .iter()
-        .map(|rec| {
-            SubCacheEntry{
-                server_id: rec.server_id,
-                subscription: SubscriptionType::Modname(rec.mod_name.clone())
-            }
-        })
-        .chain(
-            sqlx::query!(r#"SELECT * FROM subscribed_authors"#)
-                .fetch_all(&db)
-                .await?
-                .iter()
-                .filter_map(|rec| {
-                    Some(SubCacheEntry{
-                        server_id: rec.server_id?,
-                        subscription: SubscriptionType::Author(rec.author_name.clone()?)
-                    })
-                })
-        )
-        .collect::<Vec<SubCacheEntry>>();
-
-    match cache.write() {
-        Ok(mut c) => *c = mod_records,
-        Err(e) => {
-            return Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}"))));
-        },
-    };
-
-    Ok(())
-}
-
 pub async fn update_author_cache(
     cache: Arc<RwLock<Vec<String>>>,
     db: Pool<Sqlite>
@@ -447,4 +694,41 @@ pub async fn update_author_cache(
         },
     };
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn trims_changelog_to_embed_limit() {
+        assert_eq!(trim_changelog("a\nb\nc", 2), "a\nb\n<Trimmed>");
+        assert_eq!(trim_changelog("a\nb", 2), "a\nb");
+
+        let long = "ä".repeat(5000);
+        let trimmed = trim_changelog(&long, 15);
+        assert_eq!(trimmed.chars().count(), EMBED_DESCRIPTION_LIMIT);
+        assert!(trimmed.ends_with("ä\n<Trimmed>"));
+
+        let lines = format!("{}\nnext", "ö".repeat(4090));
+        let trimmed = trim_changelog(&lines, 1);
+        assert_eq!(trimmed.chars().count(), EMBED_DESCRIPTION_LIMIT);
+        assert!(trimmed.ends_with("ö\n<Trimmed>"));
+    }
+
+    #[tokio::test]
+    async fn sets_deprecated_flags() {
+        let db = crate::util::test_database().await;
+        sqlx::query!(r#"INSERT INTO mods (name, title, owner, summary, category, downloads_count, factorio_version, version, released_at)
+            VALUES ('old', 'Old', 'a', '', '', 0, '1.1', '1.0.0', 1), ('new', 'New', 'a', '', '', 0, '2.0', '1.0.0', 1)"#)
+            .execute(&db)
+            .await
+            .unwrap();
+        let flags = [("old", true), ("new", false), ("unknown", true)];
+        assert_eq!(set_deprecated_flags(&db, flags.into_iter()).await.unwrap(), 1);
+        assert_eq!(set_deprecated_flags(&db, flags.into_iter()).await.unwrap(), 0);
+        let deprecated = sqlx::query!(r#"SELECT name FROM mods WHERE deprecated"#).fetch_all(&db).await.unwrap();
+        assert_eq!(deprecated.into_iter().map(|m| m.name).collect::<Vec<_>>(), vec!["old".to_owned()]);
+    }
+}
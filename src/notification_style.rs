@@ -0,0 +1,333 @@
+use std::{collections::HashMap, sync::LazyLock};
+use poise::serenity_prelude::{Colour, Role, RoleId};
+use poise::{ChoiceParameter, CreateReply};
+use regex::Regex;
+use sqlx::{Pool, Sqlite};
+
+use crate::{Context, Error, custom_errors::CustomError,
+    mods::{make_update_embed, ModState, UpdatedMod},
+    util::{get_server_id, is_mod},
+};
+
+pub const DEFAULT_TITLE_FORMAT: &str = "{state}:\\n{title}";
+pub const DEFAULT_CHANGELOG_LINES: usize = 15;
+pub const MAX_CHANGELOG_LINES: usize = 50;
+
+static PLACEHOLDER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{(\w+)\}").unwrap());
+
+/// Per-server layout of mod update messages
+#[derive(Debug, Clone)]
+pub struct NotificationStyle {
+    pub title_format: String,
+    pub color_updated: Colour,
+    pub color_new: Colour,
+    pub show_author: bool,
+    pub show_version: bool,
+    pub show_thumbnail: bool,
+    pub changelog_lines: usize,
+    pub ping_role: Option<RoleId>,
+}
+
+impl Default for NotificationStyle {
+    fn default() -> Self {
+        Self {
+            title_format: DEFAULT_TITLE_FORMAT.to_owned(),
+            color_updated: Colour::from_rgb(0x58, 0x65, 0xF2),
+            color_new: Colour::from_rgb(0x2E, 0xCC, 0x71),
+            show_author: true,
+            show_version: true,
+            show_thumbnail: true,
+            changelog_lines: DEFAULT_CHANGELOG_LINES,
+            ping_role: None,
+        }
+    }
+}
+
+struct StyleRecord {
+    server_id: i64,
+    title_format: Option<String>,
+    color_updated: Option<i64>,
+    color_new: Option<i64>,
+    show_author: Option<bool>,
+    show_version: Option<bool>,
+    show_thumbnail: Option<bool>,
+    changelog_lines: Option<i64>,
+    ping_role: Option<i64>,
+}
+
+impl From<StyleRecord> for NotificationStyle {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn from(rec: StyleRecord) -> Self {
+        let default = Self::default();
+        Self {
+            title_format: rec.title_format.unwrap_or(default.title_format),
+            color_updated: rec.color_updated.map_or(default.color_updated, |c| Colour::new(c as u32)),
+            color_new: rec.color_new.map_or(default.color_new, |c| Colour::new(c as u32)),
+            show_author: rec.show_author.unwrap_or(default.show_author),
+            show_version: rec.show_version.unwrap_or(default.show_version),
+            show_thumbnail: rec.show_thumbnail.unwrap_or(default.show_thumbnail),
+            changelog_lines: rec.changelog_lines.map_or(default.changelog_lines, |l| l as usize),
+            ping_role: rec.ping_role.map(|r| RoleId::new(r as u64)),
+        }
+    }
+}
+
+impl NotificationStyle {
+    pub const fn color(&self, state: ModState) -> Colour {
+        match state {
+            ModState::Updated => self.color_updated,
+            ModState::New => self.color_new,
+        }
+    }
+
+    /// Fill in `{state}`, `{title}`, `{name}`, `{author}` and `{version}` in the title format.
+    /// Values are expected to be escaped already.
+    pub fn format_title(&self, state: ModState, title: &str, name: &str, author: &str, version: &str) -> String {
+        let state_text = match state {
+            ModState::Updated => "Updated mod",
+            ModState::New => "New mod",
+        };
+        PLACEHOLDER_REGEX.replace_all(&self.title_format.replace("\\n", "\n"), |caps: &regex::Captures| {
+            match &caps[1] {
+                "state" => state_text.to_owned(),
+                "title" => title.to_owned(),
+                "name" => name.to_owned(),
+                "author" => author.to_owned(),
+                "version" => version.to_owned(),
+                _ => caps[0].to_owned(),
+            }
+        })
+        .chars()
+        .take(256)
+        .collect()
+    }
+}
+
+pub async fn get_notification_styles(db: &Pool<Sqlite>) -> Result<HashMap<i64, NotificationStyle>, Error> {
+    Ok(sqlx::query_as!(StyleRecord, r#"SELECT * FROM notification_styles"#)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|rec| (rec.server_id, NotificationStyle::from(rec)))
+        .collect())
+}
+
+pub async fn get_notification_style(db: &Pool<Sqlite>, server_id: i64) -> Result<NotificationStyle, Error> {
+    Ok(sqlx::query_as!(StyleRecord, r#"SELECT * FROM notification_styles WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await?
+        .map(NotificationStyle::from)
+        .unwrap_or_default())
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+pub enum StyleColor {
+    #[name = "Updated mods"]
+    Updated,
+    #[name = "New mods"]
+    New,
+}
+
+/// Customize the layout of mod update messages
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", category="Settings",
+    subcommands("show", "title", "color", "fields", "changelog_lines", "ping_role", "reset"), subcommand_required)]
+pub async fn notify_style(
+    _: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show the current update message style with an example
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn show(
+    ctx: Context<'_>
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let style = get_notification_style(db, server_id).await?;
+    let show_changelog = sqlx::query!(r#"SELECT show_changelog FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await?
+        .and_then(|s| s.show_changelog)
+        .unwrap_or(true);
+
+    let ping_role = style.ping_role.map_or_else(|| "None".to_owned(), |r| format!("<@&{r}>"));
+    let summary = format!("**Title format:** `{}`\n**Colors:** updated #{}, new #{}\n**Author field:** {}\n**Version field:** {}\n**Thumbnail:** {}\n**Changelog lines:** {}\n**Ping role:** {ping_role}",
+        style.title_format, style.color_updated.hex(), style.color_new.hex(),
+        style.show_author, style.show_version, style.show_thumbnail, style.changelog_lines);
+    let example = UpdatedMod {
+        name: "example-mod".to_owned(),
+        title: "Example mod".to_owned(),
+        author: "Example author".to_owned(),
+        version: "1.2.3".to_owned(),
+        thumbnail: "https://assets-mod.factorio.com/assets/.thumb.png".to_owned(),
+        changelog: "**Features:**\nAdded an example\n**Bugfixes:**\nFixed an example".to_owned(),
+        released_at: 0,
+        state: ModState::Updated,
+    };
+    let embed = make_update_embed(&example, &style, show_changelog).await;
+    let builder = CreateReply::default()
+        .content(summary)
+        .embed(embed)
+        .allowed_mentions(poise::serenity_prelude::CreateAllowedMentions::new());
+    ctx.send(builder).await?;
+    Ok(())
+}
+
+/// Set the update message title. Placeholders: {state} {title} {name} {author} {version}, \n
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn title(
+    ctx: Context<'_>,
+    #[description = r"Title format, e.g. {state}: {title} {version}"]
+    #[rest]
+    format: String,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    sqlx::query!(r#"INSERT INTO notification_styles (server_id, title_format) VALUES ($1, $2)
+        ON CONFLICT (server_id) DO UPDATE SET title_format = excluded.title_format"#, server_id, format)
+        .execute(db)
+        .await?;
+    ctx.say(format!("Update message title format set to `{format}`")).await?;
+    Ok(())
+}
+
+/// Set the embed color of update messages
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn color(
+    ctx: Context<'_>,
+    #[description = "Which messages to change the color of"]
+    kind: StyleColor,
+    #[description = "Hex color code, e.g. #5865F2"]
+    hex: String,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let Ok(value) = i64::from_str_radix(hex.trim_start_matches('#'), 16) else {
+        return Err(Box::new(CustomError::new(&format!("{hex} is not a valid hex color code"))))
+    };
+    if value > 0xFF_FFFF {
+        return Err(Box::new(CustomError::new(&format!("{hex} is not a valid hex color code"))))
+    }
+    match kind {
+        StyleColor::Updated => {
+            sqlx::query!(r#"INSERT INTO notification_styles (server_id, color_updated) VALUES ($1, $2)
+                ON CONFLICT (server_id) DO UPDATE SET color_updated = excluded.color_updated"#, server_id, value)
+                .execute(db)
+                .await?;
+        },
+        StyleColor::New => {
+            sqlx::query!(r#"INSERT INTO notification_styles (server_id, color_new) VALUES ($1, $2)
+                ON CONFLICT (server_id) DO UPDATE SET color_new = excluded.color_new"#, server_id, value)
+                .execute(db)
+                .await?;
+        },
+    }
+    ctx.say(format!("Color for {} set to #{value:06X}", kind.name())).await?;
+    Ok(())
+}
+
+/// Choose which parts of update messages are shown
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn fields(
+    ctx: Context<'_>,
+    #[description = "Show the author field"]
+    author: Option<bool>,
+    #[description = "Show the version field"]
+    version: Option<bool>,
+    #[description = "Show the mod thumbnail"]
+    thumbnail: Option<bool>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    sqlx::query!(r#"INSERT INTO notification_styles (server_id, show_author, show_version, show_thumbnail) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (server_id) DO UPDATE SET
+            show_author = COALESCE(excluded.show_author, show_author),
+            show_version = COALESCE(excluded.show_version, show_version),
+            show_thumbnail = COALESCE(excluded.show_thumbnail, show_thumbnail)"#,
+        server_id, author, version, thumbnail)
+        .execute(db)
+        .await?;
+    let style = get_notification_style(db, server_id).await?;
+    ctx.say(format!("Author field: {}\nVersion field: {}\nThumbnail: {}", style.show_author, style.show_version, style.show_thumbnail)).await?;
+    Ok(())
+}
+
+/// Set how many changelog lines are shown in update messages
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn changelog_lines(
+    ctx: Context<'_>,
+    #[description = "Number of lines"]
+    #[min = 1]
+    #[max = 50]
+    lines: u8,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    if !(1..=MAX_CHANGELOG_LINES).contains(&usize::from(lines)) {
+        return Err(Box::new(CustomError::new(&format!("Changelog lines must be between 1 and {MAX_CHANGELOG_LINES}"))))
+    }
+    sqlx::query!(r#"INSERT INTO notification_styles (server_id, changelog_lines) VALUES ($1, $2)
+        ON CONFLICT (server_id) DO UPDATE SET changelog_lines = excluded.changelog_lines"#, server_id, lines)
+        .execute(db)
+        .await?;
+    ctx.say(format!("Update messages will show up to {lines} changelog lines. Use /show_changelogs to hide changelogs entirely.")).await?;
+    Ok(())
+}
+
+/// Set a role to ping when a subscribed mod updates. Leave empty to stop pinging.
+#[allow(clippy::cast_possible_wrap)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn ping_role(
+    ctx: Context<'_>,
+    #[description = "Role to ping"]
+    role: Option<Role>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let role_id = role.as_ref().map(|r| r.id.get() as i64);
+    sqlx::query!(r#"INSERT INTO notification_styles (server_id, ping_role) VALUES ($1, $2)
+        ON CONFLICT (server_id) DO UPDATE SET ping_role = excluded.ping_role"#, server_id, role_id)
+        .execute(db)
+        .await?;
+    let response = role.map_or_else(
+        || "No role will be pinged for mod updates".to_owned(),
+        |r| format!("{r} will be pinged when subscribed mods update"));
+    let builder = CreateReply::default()
+        .content(response)
+        .allowed_mentions(poise::serenity_prelude::CreateAllowedMentions::new());
+    ctx.send(builder).await?;
+    Ok(())
+}
+
+/// Reset update messages to the default style
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn reset(
+    ctx: Context<'_>
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    sqlx::query!(r#"DELETE FROM notification_styles WHERE server_id = $1"#, server_id)
+        .execute(db)
+        .await?;
+    ctx.say("Update message style reset to default").await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    #[allow(clippy::literal_string_with_formatting_args)]
+    fn formats_title_placeholders() {
+        let style = NotificationStyle{
+            title_format: r"{state}: {title}\n{version} by {author} {unknown}".to_owned(),
+            ..NotificationStyle::default()
+        };
+        let title = style.format_title(ModState::New, "Title {version}", "name", "author", "1.0.0");
+        assert_eq!(title, "New mod: Title {version}\n1.0.0 by author {unknown}");
+    }
+}
@@ -0,0 +1,336 @@
+use std::net::{IpAddr, SocketAddr};
+use hmac::{Hmac, Mac};
+use log::{error, info};
+use poise::CreateReply;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::{Pool, Sqlite};
+use tokio::{net::lookup_host, time::{sleep, Duration}};
+
+use crate::{Context, Error, custom_errors::CustomError, mods::ModState, util::{get_server_id, is_mod}};
+
+// Attempts per delivery, waiting RETRY_DELAY, 2*RETRY_DELAY, ... in between
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+const SIGNATURE_HEADER: &str = "X-Rhobot-Signature-256";
+
+/// JSON body posted to outgoing webhooks. Fields are only ever added, never renamed or removed.
+#[derive(Serialize, Debug, Clone)]
+pub struct ModUpdatePayload {
+    pub name: String,
+    pub title: String,
+    pub author: String,
+    pub version: String,
+    pub state: ModState,
+    pub changelog: String,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutgoingWebhook {
+    pub url: String,
+    pub secret: String,
+}
+
+pub async fn get_outgoing_webhooks(db: &Pool<Sqlite>) -> Result<Vec<(i64, OutgoingWebhook)>, Error> {
+    Ok(sqlx::query!(r#"SELECT server_id, url, secret FROM outgoing_webhooks"#)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|rec| (rec.server_id, OutgoingWebhook{url: rec.url, secret: rec.secret}))
+        .collect())
+}
+
+/// Hex encoded HMAC-SHA256 of `body`, keyed with the webhook secret.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    #[allow(clippy::expect_used)]   // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// Whether an address is reachable from the internet, rather than the bot's own machine or network
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let shared = ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64;
+            !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+                || ip.is_broadcast() || ip.is_documentation() || shared)
+        },
+        IpAddr::V6(ip) => ip.to_ipv4_mapped().map_or_else(
+            || !(ip.is_loopback() || ip.is_unspecified() || ip.is_unique_local() || ip.is_unicast_link_local()),
+            |ip| is_public_ip(IpAddr::V4(ip)),
+        ),
+    }
+}
+
+/// Check that a webhook URL uses https:// and only resolves to public addresses, so webhooks can not
+/// be used to reach services on the bot's own network. Returns the host name and its addresses.
+pub async fn resolve_webhook_url(url: &str) -> Result<(Option<String>, Vec<SocketAddr>), Error> {
+    let parsed = match reqwest::Url::parse(url) {
+        Ok(u) if u.scheme() == "https" => u,
+        _ => return Err(Box::new(CustomError::new("Webhook URL must be a valid https:// URL"))),
+    };
+    let port = parsed.port_or_known_default().unwrap_or(443);
+    let host = parsed.host_str().unwrap_or_default();
+    let (domain, addresses) = match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => (None, vec![SocketAddr::new(ip, port)]),
+        Err(_) => (Some(host.to_owned()), lookup_host((host, port)).await.map(Iterator::collect).unwrap_or_default()),
+    };
+    if addresses.is_empty() || !addresses.iter().all(|a| is_public_ip(a.ip())) {
+        return Err(Box::new(CustomError::new("Webhook URL must point to a public address")))
+    }
+    Ok((domain, addresses))
+}
+
+/// Post `payload` to `webhook`, retrying on connection errors, rate limits and server errors.
+/// The address is checked again on every delivery, and requests go only to the checked addresses.
+pub async fn deliver(
+    webhook: &OutgoingWebhook,
+    payload: &ModUpdatePayload,
+) -> Result<(), Error> {
+    let (domain, addresses) = resolve_webhook_url(&webhook.url).await?;
+    // Redirects could lead to any address, so they are not followed
+    let mut builder = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+    if let Some(domain) = domain {
+        builder = builder.resolve_to_addrs(&domain, &addresses);
+    }
+    deliver_with_delay(&builder.build()?, webhook, payload, RETRY_DELAY).await
+}
+
+async fn deliver_with_delay(
+    client: &reqwest::Client,
+    webhook: &OutgoingWebhook,
+    payload: &ModUpdatePayload,
+    retry_delay: Duration,
+) -> Result<(), Error> {
+    let body = serde_json::to_vec(payload)?;
+    let signature = format!("sha256={}", sign(&webhook.secret, &body));
+    let mut attempt = 1;
+    loop {
+        let result = client.post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, &signature)
+            .body(body.clone())
+            .send()
+            .await;
+        let error = match result {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                if !(status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS) {
+                    return Err(Box::new(CustomError::new(&format!("Webhook {} rejected update with HTTP status code {}", webhook.url, status.as_str()))));
+                }
+                format!("HTTP status code {}", status.as_str())
+            },
+            Err(e) => e.to_string(),
+        };
+        if attempt >= MAX_ATTEMPTS {
+            return Err(Box::new(CustomError::new(&format!("Failed to deliver update to webhook {} after {attempt} attempts: {error}", webhook.url))));
+        }
+        info!("Delivering update to webhook {} failed ({error}), retrying", webhook.url);
+        sleep(retry_delay * 2u32.pow(attempt - 1)).await;
+        attempt += 1;
+    }
+}
+
+/// Post a mod update to all given webhooks. Failures are logged per webhook.
+pub async fn deliver_all(webhooks: Vec<OutgoingWebhook>, payload: ModUpdatePayload) {
+    let deliveries = webhooks.iter().map(|webhook| deliver(webhook, &payload));
+    for result in futures::future::join_all(deliveries).await {
+        if let Err(e) = result {
+            error!("Error sending outgoing webhook: {e}");
+        }
+    }
+}
+
+/// Manage HTTP webhooks that receive mod updates as signed JSON
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", subcommands("add", "remove", "list"), subcommand_required, category="Settings")]
+pub async fn outgoing_webhooks(
+    _: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Post mod updates matching this server's subscriptions to a URL
+// Slash command only, as the reply contains the secret and prefix commands can not reply ephemerally
+#[poise::command(slash_command, guild_only, check="is_mod", ephemeral)]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "HTTPS URL to post updates to"]
+    url: String,
+    #[description = "Secret used to sign payloads. Generated if not given."]
+    secret: Option<String>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    resolve_webhook_url(&url).await?;
+    let secret = secret.unwrap_or_else(|| {
+        rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect()
+    });
+    sqlx::query!(r#"DELETE FROM outgoing_webhooks WHERE server_id = $1 AND url = $2"#, server_id, url)
+        .execute(db)
+        .await?;
+    sqlx::query!(r#"INSERT INTO outgoing_webhooks (server_id, url, secret) VALUES ($1, $2, $3)"#, server_id, url, secret)
+        .execute(db)
+        .await?;
+    let response = format!("Mod updates will be posted to <{url}>.\nPayloads are signed with HMAC-SHA256 in the `{SIGNATURE_HEADER}` header using secret `{secret}`");
+    ctx.send(CreateReply::default().content(response)).await?;
+    Ok(())
+}
+
+/// Stop posting mod updates to a URL
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", ephemeral)]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "URL of the webhook to remove"]
+    url: String,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    match sqlx::query!(r#"DELETE FROM outgoing_webhooks WHERE server_id = $1 AND url = $2"#, server_id, url)
+        .execute(db)
+        .await?
+        .rows_affected() {
+        0 => ctx.say(format!("No webhook found for <{url}>")).await?,
+        _ => ctx.say(format!("Removed webhook <{url}>")).await?,
+    };
+    Ok(())
+}
+
+/// List webhooks receiving mod updates for this server
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", ephemeral)]
+pub async fn list(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let db = &ctx.data().database;
+    let urls = sqlx::query!(r#"SELECT url FROM outgoing_webhooks WHERE server_id = $1"#, server_id)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|rec| format!("<{}>", rec.url))
+        .collect::<Vec<String>>();
+    if urls.is_empty() {
+        ctx.say("No outgoing webhooks set up for this server").await?;
+    } else {
+        ctx.say(format!("**Outgoing webhooks:**\n{}", urls.join("\n"))).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    struct StubRequest {
+        signature: Option<String>,
+        body: Vec<u8>,
+    }
+
+    // Minimal HTTP server answering each request with the next status code in `statuses`
+    async fn start_stub_server(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<StubRequest>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let requests_clone = requests.clone();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                let (header_len, content_length) = loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        let headers = String::from_utf8_lossy(&buf[..pos]).to_lowercase();
+                        let length = headers.lines()
+                            .find_map(|l| l.strip_prefix("content-length:"))
+                            .map_or(0, |l| l.trim().parse::<usize>().unwrap());
+                        break (pos + 4, length);
+                    }
+                };
+                while buf.len() < header_len + content_length {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let headers = String::from_utf8_lossy(&buf[..header_len]).to_string();
+                let signature = headers.lines()
+                    .find_map(|l| l.to_lowercase().starts_with(&SIGNATURE_HEADER.to_lowercase())
+                        .then(|| l.split_once(':').unwrap().1.trim().to_owned()));
+                requests_clone.lock().unwrap().push(StubRequest{
+                    signature,
+                    body: buf[header_len..].to_vec(),
+                });
+                let response = format!("HTTP/1.1 {status} Stub\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (format!("http://{address}/hook"), requests)
+    }
+
+    fn test_payload() -> ModUpdatePayload {
+        ModUpdatePayload {
+            name: "test-mod".to_owned(),
+            title: "Test mod".to_owned(),
+            author: "tester".to_owned(),
+            version: "1.0.1".to_owned(),
+            state: ModState::Updated,
+            changelog: "Fixed things".to_owned(),
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_non_public_urls() {
+        for url in ["http://93.184.216.34/hook", "https://127.0.0.1/hook", "https://10.1.2.3/hook", "https://192.168.0.1/hook",
+            "https://169.254.169.254/latest", "https://100.64.0.1/hook", "https://[::1]/hook", "https://[fd00::1]/hook",
+            "https://[::ffff:127.0.0.1]/hook", "https://localhost/hook", "not a url"] {
+            assert!(resolve_webhook_url(url).await.is_err(), "{url}");
+        }
+        let (domain, addresses) = resolve_webhook_url("https://93.184.216.34/hook").await.unwrap();
+        assert_eq!(domain, None);
+        assert_eq!(addresses, vec!["93.184.216.34:443".parse::<SocketAddr>().unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_payload() {
+        let (url, requests) = start_stub_server(vec![200]).await;
+        let webhook = OutgoingWebhook{url, secret: "secret".to_owned()};
+        let result = deliver_with_delay(&reqwest::Client::new(), &webhook, &test_payload(), Duration::from_millis(10)).await;
+        assert!(result.is_ok());
+
+        let requests = std::mem::take(&mut *requests.lock().unwrap());
+        assert_eq!(requests.len(), 1);
+        let expected = format!("sha256={}", sign("secret", &requests[0].body));
+        assert_eq!(requests[0].signature.as_deref(), Some(expected.as_str()));
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["name"], "test-mod");
+        assert_eq!(body["state"], "updated");
+        assert_eq!(body["timestamp"], 1_700_000_000);
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let (url, requests) = start_stub_server(vec![500, 503, 200]).await;
+        let webhook = OutgoingWebhook{url, secret: "secret".to_owned()};
+        let result = deliver_with_delay(&reqwest::Client::new(), &webhook, &test_payload(), Duration::from_millis(10)).await;
+        assert!(result.is_ok());
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let (url, requests) = start_stub_server(vec![404, 200]).await;
+        let webhook = OutgoingWebhook{url, secret: "secret".to_owned()};
+        let result = deliver_with_delay(&reqwest::Client::new(), &webhook, &test_payload(), Duration::from_millis(10)).await;
+        assert!(result.is_err());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,964 @@
+use poise::serenity_prelude::{self as serenity, Attachment, ChannelId, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed, CreateInteractionResponse};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::{collections::HashSet, time::Duration};
+use log::error;
+
+use crate::{Context, Error, custom_errors::CustomError, faq_commands, faq_sharing, mod_list, mods, outgoing_webhooks, subscriptions::Subscriptions, util::{get_server_id, is_mod}};
+
+/// Version of the `/server export` format, raised whenever older imports would lose part of an export
+pub const EXPORT_VERSION: u32 = 3;
+
+// Time to press the confirmation button of /server reset
+const CONFIRM_TIMEOUT: Duration = Duration::from_mins(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ResetScope {
+    #[name = "Settings"]
+    Settings,
+    #[name = "Subscriptions"]
+    Subscriptions,
+    #[name = "FAQs"]
+    Faqs,
+    #[name = "Everything"]
+    Everything,
+}
+
+impl ResetScope {
+    pub fn includes(self, scope: Self) -> bool {
+        self == Self::Everything || self == scope
+    }
+
+    const fn description(self) -> &'static str {
+        match self {
+            Self::Settings => "all server settings, including the notification style and outgoing webhooks",
+            Self::Subscriptions => "all subscribed mods and authors",
+            Self::Faqs => "all FAQ entries and shared FAQ namespaces",
+            Self::Everything => "all settings, subscriptions, FAQ entries and shared FAQ namespaces",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ImportMode {
+    #[name = "Merge with existing data"]
+    Merge,
+    #[name = "Replace existing data"]
+    Replace,
+}
+
+/// Everything stored for a server, as produced by `/server export`
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct ServerExport {
+    pub version: u32,
+    pub server_id: i64,
+    pub exported_at: i64,
+    #[serde(default)]
+    pub settings: Option<ServerSettings>,
+    #[serde(default)]
+    pub notification_style: Option<StyleSettings>,
+    #[serde(default)]
+    pub subscribed_mods: Vec<ExportedSubscription>,
+    #[serde(default)]
+    pub subscribed_authors: Vec<ExportedSubscription>,
+    #[serde(default)]
+    pub faqs: Vec<ExportedFaq>,
+    #[serde(default)]
+    pub faq_aliases: Vec<ExportedAlias>,
+    #[serde(default)]
+    pub outgoing_webhooks: Vec<ExportedWebhook>,
+    #[serde(default)]
+    pub faq_revisions: Vec<ExportedRevision>,
+    #[serde(default)]
+    pub faq_triggers: Vec<ExportedTrigger>,
+    #[serde(default)]
+    pub faq_suggestion_channels: Vec<i64>,
+    /// Namespaces owned by the server, with the entries published to them
+    #[serde(default)]
+    pub faq_namespaces: Vec<ExportedNamespace>,
+    /// Namespaces of other servers the server subscribes to
+    #[serde(default)]
+    pub faq_namespace_subscriptions: Vec<String>,
+}
+
+/// Row of `servers`. The update webhook itself is not exported, it is recreated when needed.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct ServerSettings {
+    pub updates_channel: Option<i64>,
+    pub modrole: Option<i64>,
+    pub show_changelog: Option<bool>,
+    pub webhook_name: Option<String>,
+    pub webhook_avatar: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct StyleSettings {
+    pub title_format: Option<String>,
+    pub color_updated: Option<i64>,
+    pub color_new: Option<i64>,
+    pub show_author: Option<bool>,
+    pub show_version: Option<bool>,
+    pub show_thumbnail: Option<bool>,
+    pub changelog_lines: Option<i64>,
+    pub ping_role: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ExportedSubscription {
+    pub name: String,
+    #[serde(default)]
+    pub ping_role: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ExportedFaq {
+    pub title: String,
+    pub contents: Option<String>,
+    pub image: Option<String>,
+    pub edit_time: i64,
+    pub author: i64,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Links to other entries in version 1 exports, imported as aliases
+    #[serde(default, skip_serializing)]
+    pub link: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ExportedAlias {
+    pub alias: String,
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ExportedWebhook {
+    pub url: String,
+    pub secret: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ExportedRevision {
+    pub title: String,
+    pub revision: i64,
+    pub contents: Option<String>,
+    pub image: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    pub edit_time: i64,
+    pub author: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ExportedTrigger {
+    pub title: String,
+    pub kind: String,
+    pub pattern: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ExportedNamespace {
+    pub name: String,
+    pub entries: Vec<String>,
+}
+
+/// Collect everything stored for a server.
+pub async fn export_server(db: &Pool<Sqlite>, server_id: i64, exported_at: i64) -> Result<ServerExport, Error> {
+    let settings = sqlx::query_as!(ServerSettings, r#"SELECT updates_channel, modrole, show_changelog, webhook_name, webhook_avatar
+        FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await?;
+    let notification_style = sqlx::query_as!(StyleSettings, r#"SELECT title_format, color_updated, color_new, show_author,
+        show_version, show_thumbnail, changelog_lines, ping_role FROM notification_styles WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await?;
+    let subscribed_mods = sqlx::query_as!(ExportedSubscription, r#"SELECT mod_name AS name, ping_role
+        FROM subscribed_mods WHERE server_id = $1 ORDER BY mod_name"#, server_id)
+        .fetch_all(db)
+        .await?;
+    let subscribed_authors = sqlx::query_as!(ExportedSubscription, r#"SELECT author_name AS "name!", ping_role
+        FROM subscribed_authors WHERE server_id = $1 AND author_name IS NOT NULL ORDER BY author_name"#, server_id)
+        .fetch_all(db)
+        .await?;
+    let faqs = sqlx::query!(r#"SELECT title, contents, image, body, category, edit_time, author FROM faq WHERE server_id = $1 ORDER BY title"#, server_id)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|f| ExportedFaq {
+            title: f.title,
+            contents: f.contents,
+            image: f.image,
+            edit_time: f.edit_time,
+            author: f.author,
+            body: f.body,
+            category: f.category,
+            link: None,
+        })
+        .collect();
+    let faq_aliases = sqlx::query_as!(ExportedAlias, r#"SELECT alias, title FROM faq_aliases WHERE server_id = $1 ORDER BY alias"#, server_id)
+        .fetch_all(db)
+        .await?;
+    let outgoing_webhooks = sqlx::query_as!(ExportedWebhook, r#"SELECT url, secret FROM outgoing_webhooks WHERE server_id = $1"#, server_id)
+        .fetch_all(db)
+        .await?;
+    let faq_revisions = sqlx::query_as!(ExportedRevision, r#"SELECT title, revision, contents, image, body, edit_time, author
+        FROM faq_revisions WHERE server_id = $1 ORDER BY title, revision"#, server_id)
+        .fetch_all(db)
+        .await?;
+    let faq_triggers = sqlx::query_as!(ExportedTrigger, r#"SELECT title, kind, pattern FROM faq_triggers
+        WHERE server_id = $1 ORDER BY title, pattern"#, server_id)
+        .fetch_all(db)
+        .await?;
+    let faq_suggestion_channels = sqlx::query!(r#"SELECT channel_id FROM faq_suggestion_channels WHERE server_id = $1 ORDER BY channel_id"#, server_id)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|c| c.channel_id)
+        .collect();
+    let mut faq_namespaces = Vec::new();
+    for namespace in sqlx::query!(r#"SELECT name FROM faq_namespaces WHERE server_id = $1 ORDER BY name"#, server_id)
+        .fetch_all(db)
+        .await?
+    {
+        let entries = sqlx::query!(r#"SELECT title FROM faq_namespace_entries WHERE namespace = $1 ORDER BY title"#, namespace.name)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|e| e.title)
+            .collect();
+        faq_namespaces.push(ExportedNamespace { name: namespace.name, entries });
+    }
+    let faq_namespace_subscriptions = sqlx::query!(r#"SELECT namespace FROM faq_namespace_subscriptions
+        WHERE server_id = $1 ORDER BY namespace"#, server_id)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|s| s.namespace)
+        .collect();
+    Ok(ServerExport {
+        version: EXPORT_VERSION,
+        server_id,
+        exported_at,
+        settings,
+        notification_style,
+        subscribed_mods,
+        subscribed_authors,
+        faqs,
+        faq_aliases,
+        outgoing_webhooks,
+        faq_revisions,
+        faq_triggers,
+        faq_suggestion_channels,
+        faq_namespaces,
+        faq_namespace_subscriptions,
+    })
+}
+
+/// What `import_server` imported, or would import on a dry run
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub settings: bool,
+    pub notification_style: bool,
+    pub subscribed_mods: usize,
+    pub subscribed_authors: usize,
+    pub faqs: usize,
+    pub faq_aliases: usize,
+    pub outgoing_webhooks: usize,
+    pub faq_revisions: usize,
+    pub faq_triggers: usize,
+    pub faq_suggestion_channels: usize,
+    pub faq_namespaces: usize,
+    pub faq_namespace_subscriptions: usize,
+}
+
+/// Restore an export into a server in a single transaction.
+/// `Merge` keeps existing data and only overwrites what the export contains,
+/// `Replace` deletes the existing data first. A dry run rolls the transaction back.
+/// The update webhook of the server is kept in both modes.
+#[allow(clippy::too_many_lines, clippy::cast_possible_truncation)]
+pub async fn import_server(
+    db: &Pool<Sqlite>,
+    subscriptions: &Subscriptions,
+    server_id: i64,
+    data: &ServerExport,
+    mode: ImportMode,
+    dry_run: bool,
+) -> Result<ImportSummary, Error> {
+    if data.version > EXPORT_VERSION {
+        return Err(Box::new(CustomError::new(&format!(
+            "This export has format version {}, but only version {EXPORT_VERSION} and older can be imported", data.version
+        ))))
+    }
+    let mut summary = ImportSummary::default();
+    let mut transaction = db.begin().await?;
+    if mode == ImportMode::Replace {
+        sqlx::query!(r#"DELETE FROM notification_styles WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM subscribed_mods WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM subscribed_authors WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        // FAQ entries with what `faq_commands::remove` deletes along with an entry, and the FAQ settings
+        sqlx::query!(r#"DELETE FROM faq WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_aliases WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_triggers WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_revisions WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_namespace_entries WHERE namespace IN (SELECT name FROM faq_namespaces WHERE server_id = $1)"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_suggestion_channels WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_namespace_subscriptions WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM outgoing_webhooks WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    match (mode, &data.settings) {
+        (ImportMode::Merge, Some(settings)) => {
+            sqlx::query!(r#"INSERT INTO servers (server_id, updates_channel, modrole, show_changelog, webhook_name, webhook_avatar)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (server_id) DO UPDATE SET
+                    updates_channel = COALESCE(excluded.updates_channel, updates_channel),
+                    modrole = COALESCE(excluded.modrole, modrole),
+                    show_changelog = COALESCE(excluded.show_changelog, show_changelog),
+                    webhook_name = COALESCE(excluded.webhook_name, webhook_name),
+                    webhook_avatar = COALESCE(excluded.webhook_avatar, webhook_avatar)"#,
+                server_id, settings.updates_channel, settings.modrole, settings.show_changelog, settings.webhook_name, settings.webhook_avatar)
+                .execute(&mut *transaction)
+                .await?;
+            summary.settings = true;
+        },
+        (ImportMode::Merge, None) => {},
+        (ImportMode::Replace, settings) => {
+            // Keep the row rather than deleting it, so the update webhook is not lost
+            let default = ServerSettings::default();
+            let settings = settings.as_ref().unwrap_or(&default);
+            sqlx::query!(r#"INSERT INTO servers (server_id, updates_channel, modrole, show_changelog, webhook_name, webhook_avatar)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (server_id) DO UPDATE SET
+                    updates_channel = excluded.updates_channel,
+                    modrole = excluded.modrole,
+                    show_changelog = excluded.show_changelog,
+                    webhook_name = excluded.webhook_name,
+                    webhook_avatar = excluded.webhook_avatar"#,
+                server_id, settings.updates_channel, settings.modrole, settings.show_changelog, settings.webhook_name, settings.webhook_avatar)
+                .execute(&mut *transaction)
+                .await?;
+            summary.settings = data.settings.is_some();
+        },
+    }
+
+    if let Some(style) = &data.notification_style {
+        sqlx::query!(r#"INSERT INTO notification_styles (server_id, title_format, color_updated, color_new, show_author,
+                show_version, show_thumbnail, changelog_lines, ping_role)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (server_id) DO UPDATE SET
+                title_format = COALESCE(excluded.title_format, title_format),
+                color_updated = COALESCE(excluded.color_updated, color_updated),
+                color_new = COALESCE(excluded.color_new, color_new),
+                show_author = COALESCE(excluded.show_author, show_author),
+                show_version = COALESCE(excluded.show_version, show_version),
+                show_thumbnail = COALESCE(excluded.show_thumbnail, show_thumbnail),
+                changelog_lines = COALESCE(excluded.changelog_lines, changelog_lines),
+                ping_role = COALESCE(excluded.ping_role, ping_role)"#,
+            server_id, style.title_format, style.color_updated, style.color_new, style.show_author,
+            style.show_version, style.show_thumbnail, style.changelog_lines, style.ping_role)
+            .execute(&mut *transaction)
+            .await?;
+        summary.notification_style = true;
+    }
+
+    // Written directly to share the transaction, the subscription cache is reloaded afterwards
+    for subscription in &data.subscribed_mods {
+        sqlx::query!(r#"INSERT INTO subscribed_mods (server_id, mod_name, ping_role) VALUES ($1, $2, $3)
+            ON CONFLICT (server_id, mod_name) DO UPDATE SET ping_role = COALESCE(excluded.ping_role, ping_role)"#,
+            server_id, subscription.name, subscription.ping_role)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    summary.subscribed_mods = data.subscribed_mods.len();
+    for subscription in &data.subscribed_authors {
+        sqlx::query!(r#"INSERT INTO subscribed_authors (server_id, author_name, ping_role) VALUES ($1, $2, $3)
+            ON CONFLICT (server_id, author_name) DO UPDATE SET ping_role = COALESCE(excluded.ping_role, ping_role)"#,
+            server_id, subscription.name, subscription.ping_role)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    summary.subscribed_authors = data.subscribed_authors.len();
+
+    let link_aliases = data.faqs.iter()
+        .filter_map(|faq| Some(ExportedAlias { alias: faq.title.clone(), title: faq.link.clone()? }))
+        .collect::<Vec<ExportedAlias>>();
+    // History is only restored for entries without one here, so it never mixes with existing revisions
+    let mut history_titles = HashSet::new();
+    for revision in &data.faq_revisions {
+        if !history_titles.contains(&revision.title) {
+            let has_history = sqlx::query!(r#"SELECT 1 AS found FROM faq_revisions WHERE server_id = $1 AND title = $2"#, server_id, revision.title)
+                .fetch_optional(&mut *transaction)
+                .await?
+                .is_some();
+            if has_history {
+                continue;
+            }
+            history_titles.insert(revision.title.clone());
+        }
+        sqlx::query!(r#"INSERT OR IGNORE INTO faq_revisions (server_id, title, revision, contents, image, body, edit_time, author)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+            server_id, revision.title, revision.revision, revision.contents, revision.image, revision.body, revision.edit_time, revision.author)
+            .execute(&mut *transaction)
+            .await?;
+        summary.faq_revisions += 1;
+    }
+    // Saved like any other edit, so the import shows up in the history of each entry
+    for faq in data.faqs.iter().filter(|faq| faq.link.is_none()) {
+        sqlx::query!(r#"DELETE FROM faq_aliases WHERE server_id = $1 AND alias = $2"#, server_id, faq.title)
+            .execute(&mut *transaction)
+            .await?;
+        let content = faq_commands::FaqContent { contents: faq.contents.clone(), image: faq.image.clone(), body: faq.body.clone() };
+        faq_commands::save_faq_in(&mut transaction, server_id, &faq.title, &content, faq.edit_time, faq.author).await?;
+        sqlx::query!(r#"UPDATE faq SET category = $3 WHERE server_id = $1 AND title = $2"#, server_id, faq.title, faq.category)
+            .execute(&mut *transaction)
+            .await?;
+        summary.faqs += 1;
+    }
+    for alias in data.faq_aliases.iter().chain(&link_aliases) {
+        sqlx::query!(r#"INSERT INTO faq_aliases (server_id, alias, title) VALUES ($1, $2, $3)
+            ON CONFLICT (server_id, alias) DO UPDATE SET title = excluded.title"#,
+            server_id, alias.alias, alias.title)
+            .execute(&mut *transaction)
+            .await?;
+        summary.faq_aliases += 1;
+    }
+
+    for trigger in &data.faq_triggers {
+        summary.faq_triggers += sqlx::query!(r#"INSERT OR IGNORE INTO faq_triggers (server_id, title, kind, pattern) VALUES ($1, $2, $3, $4)"#,
+            server_id, trigger.title, trigger.kind, trigger.pattern)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected() as usize;
+    }
+    for channel_id in &data.faq_suggestion_channels {
+        summary.faq_suggestion_channels += sqlx::query!(r#"INSERT OR IGNORE INTO faq_suggestion_channels (server_id, channel_id) VALUES ($1, $2)"#,
+            server_id, channel_id)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected() as usize;
+    }
+    for namespace in &data.faq_namespaces {
+        // Namespace names are shared by all servers, names taken by another server are skipped
+        let Ok(name) = faq_sharing::namespace_name(&namespace.name) else {
+            continue;
+        };
+        sqlx::query!(r#"INSERT OR IGNORE INTO faq_namespaces (name, server_id) VALUES ($1, $2)"#, name, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        let owner = sqlx::query!(r#"SELECT server_id FROM faq_namespaces WHERE name = $1"#, name)
+            .fetch_one(&mut *transaction)
+            .await?
+            .server_id;
+        if owner != server_id {
+            continue;
+        }
+        for title in &namespace.entries {
+            sqlx::query!(r#"INSERT OR IGNORE INTO faq_namespace_entries (namespace, title) VALUES ($1, $2)"#, name, title)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        summary.faq_namespaces += 1;
+    }
+    for namespace in &data.faq_namespace_subscriptions {
+        summary.faq_namespace_subscriptions += sqlx::query!(r#"INSERT OR IGNORE INTO faq_namespace_subscriptions (server_id, namespace)
+            SELECT $1, name FROM faq_namespaces WHERE name = $2"#, server_id, namespace)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected() as usize;
+    }
+
+    for webhook in &data.outgoing_webhooks {
+        summary.outgoing_webhooks += sqlx::query!(r#"INSERT INTO outgoing_webhooks (server_id, url, secret)
+            SELECT $1, $2, $3 WHERE NOT EXISTS (SELECT 1 FROM outgoing_webhooks WHERE server_id = $1 AND url = $2)"#,
+            server_id, webhook.url, webhook.secret)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected() as usize;
+    }
+
+    if dry_run {
+        transaction.rollback().await?;
+    } else {
+        transaction.commit().await?;
+        subscriptions.reload().await?;
+    }
+    Ok(summary)
+}
+
+/// Remove channels that are not in `channels` from an export, so an import can not make the bot post
+/// in another server. Returns the number of channels removed.
+pub fn drop_foreign_channels(data: &mut ServerExport, channels: &HashSet<i64>) -> usize {
+    let mut dropped = 0;
+    if let Some(settings) = &mut data.settings {
+        if settings.updates_channel.is_some_and(|c| !channels.contains(&c)) {
+            settings.updates_channel = None;
+            dropped += 1;
+        }
+    }
+    let count = data.faq_suggestion_channels.len();
+    data.faq_suggestion_channels.retain(|c| channels.contains(c));
+    dropped + count - data.faq_suggestion_channels.len()
+}
+
+/// Delete the data of a server covered by `scope`.
+/// The update webhook is not deleted from Discord, that is up to the caller.
+pub async fn delete_server_data(db: &Pool<Sqlite>, subscriptions: &Subscriptions, server_id: i64, scope: ResetScope) -> Result<(), Error> {
+    let mut transaction = db.begin().await?;
+    if scope.includes(ResetScope::Settings) {
+        sqlx::query!(r#"DELETE FROM servers WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM notification_styles WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM outgoing_webhooks WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    if scope.includes(ResetScope::Faqs) {
+        sqlx::query!(r#"DELETE FROM faq WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_revisions WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_aliases WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_namespace_subscriptions
+            WHERE server_id = $1 OR namespace IN (SELECT name FROM faq_namespaces WHERE server_id = $1)"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_namespace_entries WHERE namespace IN (SELECT name FROM faq_namespaces WHERE server_id = $1)"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_namespaces WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_usage WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_triggers WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM faq_suggestion_channels WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    transaction.commit().await?;
+    if scope.includes(ResetScope::Subscriptions) {
+        subscriptions.clear_server(server_id).await?;
+    }
+    Ok(())
+}
+
+/// Manage all data stored for this server
+#[allow(clippy::unused_async)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod", subcommands("reset", "export", "import"), subcommand_required, category="Settings")]
+pub async fn server(
+    _: Context<'_>
+) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Delete settings, subscriptions or FAQ entries of this server
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn reset(
+    ctx: Context<'_>,
+    #[description = "What to delete"]
+    scope: ResetScope,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let ctx_id = ctx.id();
+    let confirm_id = format!("{ctx_id}confirm");
+    let cancel_id = format!("{ctx_id}cancel");
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new(&confirm_id).label("Delete").style(serenity::ButtonStyle::Danger),
+        CreateButton::new(&cancel_id).label("Cancel").style(serenity::ButtonStyle::Secondary),
+    ]);
+    let reply = ctx.send(CreateReply::default()
+        .content(format!("This permanently deletes {} of this server. Are you sure?", scope.description()))
+        .components(vec![buttons]))
+        .await?;
+
+    let press = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
+        .timeout(CONFIRM_TIMEOUT)
+        .await;
+    if let Some(press) = &press {
+        press.create_response(ctx, CreateInteractionResponse::Acknowledge).await?;
+    }
+    if press.is_none_or(|p| p.data.custom_id != confirm_id) {
+        reply.edit(ctx, CreateReply::default().content("Reset cancelled, nothing was deleted").components(vec![])).await?;
+        return Ok(())
+    }
+
+    let db = &ctx.data().database;
+    if scope.includes(ResetScope::Settings) {
+        let webhook_url = sqlx::query!(r#"SELECT webhook_url FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await?
+            .and_then(|s| s.webhook_url);
+        if let Some(url) = webhook_url {
+            mods::delete_update_webhook(&url, &ctx.serenity_context().http).await;
+        }
+    }
+    delete_server_data(db, &ctx.data().subscriptions, server_id, scope).await?;
+    if let Err(e) = ctx.data().faq_suggestions.reload().await {
+        error!("Error while updating faq suggestion cache: {e}");
+    }
+    reply.edit(ctx, CreateReply::default().content(format!("Deleted {}", scope.description())).components(vec![])).await?;
+    Ok(())
+}
+
+/// Download everything stored for this server as a JSON file
+// Slash command only, as the file contains webhook secrets and prefix commands can not reply ephemerally
+#[poise::command(slash_command, guild_only, check="is_mod", ephemeral)]
+pub async fn export(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let data = export_server(&ctx.data().database, server_id, ctx.created_at().timestamp()).await?;
+    let summary = format!(
+        "Exported {} subscribed mods, {} subscribed authors, {} FAQ entries, {} shared FAQ namespaces and {} outgoing webhooks.\n\
+        The file contains webhook secrets, keep it private.",
+        data.subscribed_mods.len(), data.subscribed_authors.len(), data.faqs.len(), data.faq_namespaces.len(), data.outgoing_webhooks.len()
+    );
+    let attachment = CreateAttachment::bytes(serde_json::to_vec_pretty(&data)?, format!("server-{server_id}.json"));
+    ctx.send(CreateReply::default().content(summary).attachment(attachment)).await?;
+    Ok(())
+}
+
+/// Restore data from a /server export file into this server
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_wrap, clippy::too_many_lines)]
+#[poise::command(prefix_command, slash_command, guild_only, check="is_mod")]
+pub async fn import(
+    ctx: Context<'_>,
+    #[description = "File created by /server export"]
+    file: Attachment,
+    #[description = "Merge with or replace the existing data, merges by default"]
+    mode: Option<ImportMode>,
+    #[description = "Only show what would be imported"]
+    dry_run: Option<bool>,
+) -> Result<(), Error> {
+    let server_id = get_server_id(ctx)?;
+    let mode = mode.unwrap_or(ImportMode::Merge);
+    let dry_run = dry_run.unwrap_or(false);
+    let contents = mod_list::download_text_attachment(&file).await?;
+    let Ok(mut data) = serde_json::from_str::<ServerExport>(&contents) else {
+        return Err(Box::new(CustomError::new(&format!("{} is not a file created by /server export", file.filename))))
+    };
+    // The file can be edited, so channels and webhooks get the same checks as when they are set by command
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err(Box::new(CustomError::new("Could not get server ID")))
+    };
+    let channels = guild_id.channels(ctx).await?
+        .into_keys()
+        .map(|c| c.get() as i64)
+        .collect::<HashSet<i64>>();
+    let skipped_channels = drop_foreign_channels(&mut data, &channels);
+    let imported_webhooks = std::mem::take(&mut data.outgoing_webhooks);
+    let mut skipped_webhooks = 0;
+    for webhook in imported_webhooks {
+        if outgoing_webhooks::resolve_webhook_url(&webhook.url).await.is_ok() {
+            data.outgoing_webhooks.push(webhook);
+        } else {
+            skipped_webhooks += 1;
+        }
+    }
+
+    let db = &ctx.data().database;
+    let old_server = sqlx::query!(r#"SELECT updates_channel, webhook_url FROM servers WHERE server_id = $1"#, server_id)
+        .fetch_optional(db)
+        .await?;
+    let summary = import_server(db, &ctx.data().subscriptions, server_id, &data, mode, dry_run).await?;
+
+    if !dry_run {
+        if let Err(e) = faq_commands::update_faq_cache(ctx.data().faq_cache.clone(), db.clone()).await {
+            error!("Error while updating faq cache: {e}");
+        }
+        if let Err(e) = ctx.data().faq_suggestions.reload().await {
+            error!("Error while updating faq suggestion cache: {e}");
+        }
+        // Move the update webhook along if the import changed the updates channel.
+        // The import is already done, so a failure only turns webhook delivery off.
+        let new_channel = sqlx::query!(r#"SELECT updates_channel FROM servers WHERE server_id = $1"#, server_id)
+            .fetch_optional(db)
+            .await?
+            .and_then(|s| s.updates_channel);
+        if let Some(old_server) = old_server {
+            if let (Some(old_url), Some(channel_id)) = (old_server.webhook_url, new_channel) {
+                if old_server.updates_channel != Some(channel_id) {
+                    let http = &ctx.serenity_context().http;
+                    let url = match mods::create_update_webhook(ChannelId::new(channel_id as u64), http).await {
+                        Ok(url) => Some(url),
+                        Err(e) => {
+                            error!("Error while moving update webhook of server {server_id} after import: {e}");
+                            None
+                        },
+                    };
+                    sqlx::query!(r#"UPDATE servers SET webhook_url = $1 WHERE server_id = $2"#, url, server_id)
+                        .execute(db)
+                        .await?;
+                    mods::delete_update_webhook(&old_url, http).await;
+                }
+            }
+        }
+    }
+
+    let yes_no = |b: bool| if b { "Yes" } else { "No" };
+    let title = if dry_run { "Import preview, nothing was changed" } else { "Import complete" };
+    let mut embed = CreateEmbed::new()
+        .title(title)
+        .description(match mode {
+            ImportMode::Merge => "Existing data is kept, entries from the export overwrite entries with the same name.",
+            ImportMode::Replace => "Existing settings, subscriptions, FAQ entries, FAQ sharing and outgoing webhooks are replaced.",
+        })
+        .field("Settings", yes_no(summary.settings), true)
+        .field("Notification style", yes_no(summary.notification_style), true)
+        .field("Outgoing webhooks", summary.outgoing_webhooks.to_string(), true)
+        .field("Subscribed mods", summary.subscribed_mods.to_string(), true)
+        .field("Subscribed authors", summary.subscribed_authors.to_string(), true)
+        .field("FAQ entries", summary.faqs.to_string(), true)
+        .field("FAQ aliases", summary.faq_aliases.to_string(), true)
+        .field("FAQ revisions", summary.faq_revisions.to_string(), true)
+        .field("FAQ triggers", summary.faq_triggers.to_string(), true)
+        .field("FAQ suggestion channels", summary.faq_suggestion_channels.to_string(), true)
+        .field("Shared FAQ namespaces", summary.faq_namespaces.to_string(), true)
+        .field("FAQ namespace subscriptions", summary.faq_namespace_subscriptions.to_string(), true);
+    if data.server_id != server_id {
+        embed = embed.field("Note", "This export is from a different server, imported roles might not exist here.", false);
+    }
+    if skipped_channels > 0 || skipped_webhooks > 0 {
+        embed = embed.field("Skipped", format!(
+            "{skipped_channels} channels that are not in this server and {skipped_webhooks} outgoing webhooks without a public https:// URL"
+        ), false);
+    }
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::util::test_database;
+
+    #[tokio::test]
+    async fn reset_scopes_only_delete_their_data() {
+        let db = test_database().await;
+        let subscriptions = Subscriptions::new(db.clone());
+        for server_id in [1, 2] {
+            sqlx::query!(r#"INSERT INTO servers (server_id, updates_channel) VALUES ($1, 10)"#, server_id).execute(&db).await.unwrap();
+            sqlx::query!(r#"INSERT INTO faq (server_id, title, contents, edit_time, author) VALUES ($1, 'Faq', 'text', 0, 5)"#, server_id)
+                .execute(&db).await.unwrap();
+            subscriptions.subscribe_mod(server_id, "flib", Some(3)).await.unwrap();
+        }
+
+        let export = export_server(&db, 1, 100).await.unwrap();
+        assert_eq!(export.settings.as_ref().and_then(|s| s.updates_channel), Some(10));
+        assert_eq!(export.subscribed_mods, vec![ExportedSubscription { name: "flib".to_owned(), ping_role: Some(3) }]);
+        assert_eq!(export.faqs.len(), 1);
+        let json = serde_json::to_string(&export).unwrap();
+        assert_eq!(serde_json::from_str::<ServerExport>(&json).unwrap(), export);
+
+        delete_server_data(&db, &subscriptions, 1, ResetScope::Faqs).await.unwrap();
+        let export = export_server(&db, 1, 100).await.unwrap();
+        assert!(export.faqs.is_empty());
+        assert!(export.settings.is_some());
+        assert_eq!(export.subscribed_mods.len(), 1);
+
+        delete_server_data(&db, &subscriptions, 1, ResetScope::Everything).await.unwrap();
+        let export = export_server(&db, 1, 100).await.unwrap();
+        assert_eq!(export, ServerExport { version: EXPORT_VERSION, server_id: 1, exported_at: 100, ..Default::default() });
+        let other = export_server(&db, 2, 100).await.unwrap();
+        assert!(other.settings.is_some());
+        assert_eq!(other.faqs.len(), 1);
+        assert_eq!(other.subscribed_mods.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn import_merges_replaces_and_dry_runs() {
+        let db = test_database().await;
+        let subscriptions = Subscriptions::new(db.clone());
+        sqlx::query!(r#"INSERT INTO servers (server_id, updates_channel, modrole, webhook_url) VALUES (1, 10, 20, 'https://hook')"#)
+            .execute(&db).await.unwrap();
+        subscriptions.subscribe_mod(1, "flib", Some(3)).await.unwrap();
+        subscriptions.subscribe_author(1, "raiguard", None).await.unwrap();
+
+        let data = ServerExport {
+            version: EXPORT_VERSION,
+            server_id: 2,
+            settings: Some(ServerSettings { updates_channel: Some(11), ..Default::default() }),
+            subscribed_mods: vec![
+                ExportedSubscription { name: "flib".to_owned(), ping_role: None },
+                ExportedSubscription { name: "Krastorio2".to_owned(), ping_role: None },
+            ],
+            ..Default::default()
+        };
+
+        import_server(&db, &subscriptions, 1, &data, ImportMode::Replace, true).await.unwrap();
+        assert_eq!(export_server(&db, 1, 0).await.unwrap().subscribed_mods.len(), 1);
+
+        let summary = import_server(&db, &subscriptions, 1, &data, ImportMode::Merge, false).await.unwrap();
+        assert_eq!(summary.subscribed_mods, 2);
+        let export = export_server(&db, 1, 0).await.unwrap();
+        let settings = export.settings.unwrap();
+        assert_eq!((settings.updates_channel, settings.modrole), (Some(11), Some(20)));
+        assert_eq!(export.subscribed_mods, vec![
+            ExportedSubscription { name: "Krastorio2".to_owned(), ping_role: None },
+            ExportedSubscription { name: "flib".to_owned(), ping_role: Some(3) },
+        ]);
+        assert_eq!(export.subscribed_authors.len(), 1);
+        assert_eq!(subscriptions.cached().unwrap().len(), 3);
+
+        import_server(&db, &subscriptions, 1, &data, ImportMode::Replace, false).await.unwrap();
+        let export = export_server(&db, 1, 0).await.unwrap();
+        assert_eq!(export.settings.unwrap().modrole, None);
+        assert_eq!(export.subscribed_mods.iter().map(|s| s.ping_role).collect::<Vec<_>>(), vec![None, None]);
+        assert!(export.subscribed_authors.is_empty());
+        let webhook_url = sqlx::query!(r#"SELECT webhook_url FROM servers WHERE server_id = 1"#).fetch_one(&db).await.unwrap().webhook_url;
+        assert_eq!(webhook_url.as_deref(), Some("https://hook"));
+
+        let newer = ServerExport { version: EXPORT_VERSION + 1, ..Default::default() };
+        assert!(import_server(&db, &subscriptions, 1, &newer, ImportMode::Merge, false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn import_turns_version_1_links_into_aliases() {
+        let db = test_database().await;
+        let subscriptions = Subscriptions::new(db.clone());
+        let json = r#"{"version": 1, "server_id": 1, "exported_at": 0, "faqs": [
+            {"title": "Mods", "contents": "text", "image": null, "edit_time": 0, "author": 5, "link": null},
+            {"title": "Mod", "contents": null, "image": null, "edit_time": 0, "author": 5, "link": "Mods"}
+        ]}"#;
+        let data = serde_json::from_str::<ServerExport>(json).unwrap();
+        let summary = import_server(&db, &subscriptions, 1, &data, ImportMode::Merge, false).await.unwrap();
+        assert_eq!((summary.faqs, summary.faq_aliases), (1, 1));
+        let export = export_server(&db, 1, 0).await.unwrap();
+        assert_eq!(export.faqs.len(), 1);
+        assert_eq!(export.faq_aliases, vec![ExportedAlias { alias: "Mod".to_owned(), title: "Mods".to_owned() }]);
+    }
+
+    #[tokio::test]
+    async fn exports_and_imports_faq_extras() {
+        let db = test_database().await;
+        let subscriptions = Subscriptions::new(db.clone());
+        let content = faq_commands::FaqContent { contents: Some("text".to_owned()), ..Default::default() };
+        faq_commands::save_faq(&db, 1, "Belts", &content, 0, 5).await.unwrap();
+        faq_commands::save_faq(&db, 1, "Belts", &content, 10, 5).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_triggers (server_id, title, kind, pattern) VALUES (1, 'Belts', 'keyword', 'belt')"#).execute(&db).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_suggestion_channels (server_id, channel_id) VALUES (1, 7)"#).execute(&db).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_namespaces (name, server_id) VALUES ('mine', 1), ('other', 3)"#).execute(&db).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_namespace_entries (namespace, title) VALUES ('mine', 'Belts')"#).execute(&db).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_namespace_subscriptions (server_id, namespace) VALUES (1, 'other')"#).execute(&db).await.unwrap();
+
+        let export = export_server(&db, 1, 100).await.unwrap();
+        assert_eq!(export.faq_revisions.iter().map(|r| r.revision).collect::<Vec<i64>>(), vec![1, 2]);
+        assert_eq!(export.faq_triggers, vec![ExportedTrigger { title: "Belts".to_owned(), kind: "keyword".to_owned(), pattern: "belt".to_owned() }]);
+        assert_eq!(export.faq_suggestion_channels, vec![7]);
+        assert_eq!(export.faq_namespaces, vec![ExportedNamespace { name: "mine".to_owned(), entries: vec!["Belts".to_owned()] }]);
+        assert_eq!(export.faq_namespace_subscriptions, vec!["other"]);
+
+        // Namespaces keep their owner, another server can not take them over
+        let summary = import_server(&db, &subscriptions, 2, &export, ImportMode::Merge, false).await.unwrap();
+        assert_eq!((summary.faq_triggers, summary.faq_suggestion_channels), (1, 1));
+        assert_eq!((summary.faq_namespaces, summary.faq_namespace_subscriptions), (0, 1));
+        let imported = export_server(&db, 2, 100).await.unwrap();
+        assert!(imported.faq_namespaces.is_empty());
+        assert_eq!(imported.faq_triggers, export.faq_triggers);
+
+        delete_server_data(&db, &subscriptions, 1, ResetScope::Faqs).await.unwrap();
+        import_server(&db, &subscriptions, 1, &export, ImportMode::Merge, false).await.unwrap();
+        let restored = export_server(&db, 1, 100).await.unwrap();
+        assert_eq!(restored.faq_namespaces, export.faq_namespaces);
+        assert_eq!(restored.faq_namespace_subscriptions, export.faq_namespace_subscriptions);
+        assert_eq!(restored.faq_suggestion_channels, export.faq_suggestion_channels);
+    }
+
+    async fn revision_contents(db: &Pool<Sqlite>, title: &str) -> Vec<String> {
+        sqlx::query!(r#"SELECT contents FROM faq_revisions WHERE server_id = 1 AND title = $1 ORDER BY revision"#, title)
+            .fetch_all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|r| r.contents)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn import_records_revisions_and_replaces_faq_data() {
+        let db = test_database().await;
+        let subscriptions = Subscriptions::new(db.clone());
+        let content = faq_commands::FaqContent { contents: Some("old".to_owned()), ..Default::default() };
+        faq_commands::save_faq(&db, 1, "Belts", &content, 0, 5).await.unwrap();
+        faq_commands::save_faq(&db, 1, "Trains", &content, 0, 5).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_triggers (server_id, title, kind, pattern) VALUES (1, 'Trains', 'keyword', 'train')"#).execute(&db).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_namespaces (name, server_id) VALUES ('mine', 1)"#).execute(&db).await.unwrap();
+        sqlx::query!(r#"INSERT INTO faq_namespace_entries (namespace, title) VALUES ('mine', 'Trains')"#).execute(&db).await.unwrap();
+
+        let faq = |title: &str, contents: &str| ExportedFaq {
+            title: title.to_owned(),
+            contents: Some(contents.to_owned()),
+            image: None,
+            edit_time: 50,
+            author: 6,
+            body: None,
+            category: None,
+            link: None,
+        };
+        let data = ServerExport { version: EXPORT_VERSION, server_id: 2, faqs: vec![faq("Belts", "new"), faq("Robots", "fly")], ..Default::default() };
+        import_server(&db, &subscriptions, 1, &data, ImportMode::Merge, false).await.unwrap();
+        assert_eq!(revision_contents(&db, "Belts").await, vec!["old", "new"]);
+        assert_eq!(revision_contents(&db, "Robots").await, vec!["fly"]);
+
+        import_server(&db, &subscriptions, 1, &data, ImportMode::Replace, false).await.unwrap();
+        let export = export_server(&db, 1, 0).await.unwrap();
+        assert_eq!(export.faqs.iter().map(|f| f.title.as_str()).collect::<Vec<&str>>(), vec!["Belts", "Robots"]);
+        assert!(export.faq_triggers.is_empty());
+        assert_eq!(export.faq_namespaces, vec![ExportedNamespace { name: "mine".to_owned(), entries: vec![] }]);
+        assert!(revision_contents(&db, "Trains").await.is_empty());
+        assert_eq!(revision_contents(&db, "Belts").await, vec!["new"]);
+    }
+
+    #[test]
+    fn drops_foreign_channels() {
+        let mut data = ServerExport {
+            settings: Some(ServerSettings { updates_channel: Some(99), modrole: Some(20), ..Default::default() }),
+            faq_suggestion_channels: vec![7, 98, 8],
+            ..Default::default()
+        };
+        assert_eq!(drop_foreign_channels(&mut data, &HashSet::from([7, 8, 10])), 2);
+        assert_eq!(data.settings.as_ref().map(|s| (s.updates_channel, s.modrole)), Some((None, Some(20))));
+        assert_eq!(data.faq_suggestion_channels, vec![7, 8]);
+
+        data.settings = Some(ServerSettings { updates_channel: Some(10), ..Default::default() });
+        assert_eq!(drop_foreign_channels(&mut data, &HashSet::from([7, 8, 10])), 0);
+        assert_eq!(data.settings.and_then(|s| s.updates_channel), Some(10));
+    }
+}
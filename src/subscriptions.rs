@@ -0,0 +1,288 @@
+use std::sync::{Arc, RwLock};
+use sqlx::{Pool, Sqlite};
+
+use crate::{Error, custom_errors::CustomError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionType {
+    Author(String),
+    Modname(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubCacheEntry{
+    pub server_id: i64,
+    pub subscription: SubscriptionType
+}
+
+/// Subscribed mod with its details from the `mods` table, if the mod is known
+#[derive(Debug, Clone)]
+pub struct SubscribedModDetails {
+    pub name: String,
+    pub ping_role: Option<i64>,
+    pub title: Option<String>,
+    pub version: Option<String>,
+    pub released_at: Option<i64>,
+}
+
+/// Subscribed author with the number of mods they own
+#[derive(Debug, Clone)]
+pub struct SubscribedAuthorDetails {
+    pub name: String,
+    pub ping_role: Option<i64>,
+    pub mod_count: i64,
+}
+
+/// Owns the `subscribed_mods` and `subscribed_authors` tables together with their cache.
+/// All changes to subscriptions go through here so both stay in sync.
+#[derive(Debug, Clone)]
+pub struct Subscriptions {
+    db: Pool<Sqlite>,
+    cache: Arc<RwLock<Vec<SubCacheEntry>>>,
+}
+
+impl Subscriptions {
+    pub fn new(db: Pool<Sqlite>) -> Self {
+        Self { db, cache: Arc::new(RwLock::new(Vec::new())) }
+    }
+
+    /// Snapshot of the cached subscriptions of all servers
+    pub fn cached(&self) -> Result<Vec<SubCacheEntry>, Error> {
+        match self.cache.read() {
+            Ok(c) => Ok(c.clone()),
+            Err(e) => Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}")))),
+        }
+    }
+
+    fn update_cache(&self, update: impl FnOnce(&mut Vec<SubCacheEntry>)) -> Result<(), Error> {
+        match self.cache.write() {
+            Ok(mut c) => {
+                update(&mut c);
+                Ok(())
+            },
+            Err(e) => Err(Box::new(CustomError::new(&format!("Error acquiring cache: {e}")))),
+        }
+    }
+
+    /// Reload the cache from the database.
+    pub async fn reload(&self) -> Result<(), Error> {
+        let records = sqlx::query!(r#"SELECT server_id, mod_name FROM subscribed_mods"#)
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(|rec| SubCacheEntry{
+                server_id: rec.server_id,
+                subscription: SubscriptionType::Modname(rec.mod_name),
+            })
+            .chain(
+                sqlx::query!(r#"SELECT server_id, author_name FROM subscribed_authors"#)
+                    .fetch_all(&self.db)
+                    .await?
+                    .into_iter()
+                    .filter_map(|rec| Some(SubCacheEntry{
+                        server_id: rec.server_id?,
+                        subscription: SubscriptionType::Author(rec.author_name?),
+                    }))
+            )
+            .collect::<Vec<SubCacheEntry>>();
+        self.update_cache(|c| *c = records)
+    }
+
+    /// Subscribed mods of a server, with the role to ping for each
+    pub async fn get_mods(&self, server_id: i64) -> Result<Vec<(String, Option<i64>)>, Error> {
+        Ok(sqlx::query!(r#"SELECT mod_name, ping_role FROM subscribed_mods WHERE server_id = $1"#, server_id)
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(|m| (m.mod_name, m.ping_role))
+            .collect())
+    }
+
+    /// Subscribed authors of a server, with the role to ping for each
+    pub async fn get_authors(&self, server_id: i64) -> Result<Vec<(String, Option<i64>)>, Error> {
+        Ok(sqlx::query!(r#"SELECT author_name, ping_role FROM subscribed_authors WHERE server_id = $1"#, server_id)
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .filter_map(|m| Some((m.author_name?, m.ping_role)))
+            .collect())
+    }
+
+    /// Subscribed mods of a server joined with their latest release, sorted by name
+    pub async fn get_mod_details(&self, server_id: i64) -> Result<Vec<SubscribedModDetails>, Error> {
+        Ok(sqlx::query_as!(SubscribedModDetails, r#"
+            SELECT s.mod_name AS name, s.ping_role, m.title, m.version, m.released_at AS "released_at?"
+            FROM subscribed_mods s LEFT JOIN mods m ON m.name = s.mod_name
+            WHERE s.server_id = $1
+            ORDER BY s.mod_name COLLATE NOCASE"#, server_id)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    /// Subscribed authors of a server with their mod counts, sorted by name
+    pub async fn get_author_details(&self, server_id: i64) -> Result<Vec<SubscribedAuthorDetails>, Error> {
+        Ok(sqlx::query_as!(SubscribedAuthorDetails, r#"
+            SELECT s.author_name AS "name!", s.ping_role, COUNT(m.name) AS "mod_count!: i64"
+            FROM subscribed_authors s LEFT JOIN mods m ON m.owner = s.author_name
+            WHERE s.server_id = $1 AND s.author_name IS NOT NULL
+            GROUP BY s.author_name, s.ping_role
+            ORDER BY s.author_name COLLATE NOCASE"#, server_id)
+            .fetch_all(&self.db)
+            .await?)
+    }
+
+    /// Subscribe to a mod, or update the ping role of an existing subscription.
+    /// Returns `true` if the subscription is new.
+    pub async fn subscribe_mod(&self, server_id: i64, name: &str, ping_role: Option<i64>) -> Result<bool, Error> {
+        let mut transaction = self.db.begin().await?;
+        let added = sqlx::query!(r#"INSERT INTO subscribed_mods (server_id, mod_name, ping_role) VALUES ($1, $2, $3)
+            ON CONFLICT (server_id, mod_name) DO NOTHING"#, server_id, name, ping_role)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected() > 0;
+        if !added {
+            sqlx::query!(r#"UPDATE subscribed_mods SET ping_role = $3 WHERE server_id = $1 AND mod_name = $2"#, server_id, name, ping_role)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        if added {
+            self.update_cache(|c| c.push(SubCacheEntry{
+                server_id,
+                subscription: SubscriptionType::Modname(name.to_owned()),
+            }))?;
+        }
+        Ok(added)
+    }
+
+    /// Subscribe to an author, or update the ping role of an existing subscription.
+    /// Returns `true` if the subscription is new.
+    pub async fn subscribe_author(&self, server_id: i64, name: &str, ping_role: Option<i64>) -> Result<bool, Error> {
+        let mut transaction = self.db.begin().await?;
+        let added = sqlx::query!(r#"INSERT INTO subscribed_authors (server_id, author_name, ping_role) VALUES ($1, $2, $3)
+            ON CONFLICT (server_id, author_name) DO NOTHING"#, server_id, name, ping_role)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected() > 0;
+        if !added {
+            sqlx::query!(r#"UPDATE subscribed_authors SET ping_role = $3 WHERE server_id = $1 AND author_name = $2"#, server_id, name, ping_role)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        if added {
+            self.update_cache(|c| c.push(SubCacheEntry{
+                server_id,
+                subscription: SubscriptionType::Author(name.to_owned()),
+            }))?;
+        }
+        Ok(added)
+    }
+
+    /// Returns `false` if the server was not subscribed to the mod.
+    pub async fn unsubscribe_mod(&self, server_id: i64, name: &str) -> Result<bool, Error> {
+        let removed = sqlx::query!(r#"DELETE FROM subscribed_mods WHERE server_id = $1 AND mod_name = $2"#, server_id, name)
+            .execute(&self.db)
+            .await?
+            .rows_affected() > 0;
+        self.update_cache(|c| c.retain(|entry| {
+            entry.server_id != server_id || !matches!(&entry.subscription, SubscriptionType::Modname(m) if m == name)
+        }))?;
+        Ok(removed)
+    }
+
+    /// Returns `false` if the server was not subscribed to the author.
+    pub async fn unsubscribe_author(&self, server_id: i64, name: &str) -> Result<bool, Error> {
+        let removed = sqlx::query!(r#"DELETE FROM subscribed_authors WHERE server_id = $1 AND author_name = $2"#, server_id, name)
+            .execute(&self.db)
+            .await?
+            .rows_affected() > 0;
+        self.update_cache(|c| c.retain(|entry| {
+            entry.server_id != server_id || !matches!(&entry.subscription, SubscriptionType::Author(a) if a == name)
+        }))?;
+        Ok(removed)
+    }
+
+    /// Remove all subscriptions of a server.
+    pub async fn clear_server(&self, server_id: i64) -> Result<(), Error> {
+        let mut transaction = self.db.begin().await?;
+        sqlx::query!(r#"DELETE FROM subscribed_mods WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(r#"DELETE FROM subscribed_authors WHERE server_id = $1"#, server_id)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        self.update_cache(|c| c.retain(|entry| entry.server_id != server_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::util::test_database;
+
+    async fn test_service() -> Subscriptions {
+        Subscriptions::new(test_database().await)
+    }
+
+    fn entry(server_id: i64, subscription: SubscriptionType) -> SubCacheEntry {
+        SubCacheEntry { server_id, subscription }
+    }
+
+    #[tokio::test]
+    async fn subscribe_updates_database_and_cache() {
+        let subs = test_service().await;
+        assert!(subs.subscribe_mod(1, "flib", None).await.unwrap());
+        assert!(subs.subscribe_author(1, "raiguard", None).await.unwrap());
+        assert_eq!(subs.get_mods(1).await.unwrap(), vec![("flib".to_owned(), None)]);
+        assert_eq!(subs.get_authors(1).await.unwrap(), vec![("raiguard".to_owned(), None)]);
+        assert_eq!(subs.cached().unwrap(), vec![
+            entry(1, SubscriptionType::Modname("flib".to_owned())),
+            entry(1, SubscriptionType::Author("raiguard".to_owned())),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn resubscribe_updates_ping_role() {
+        let subs = test_service().await;
+        assert!(subs.subscribe_mod(1, "flib", None).await.unwrap());
+        assert!(!subs.subscribe_mod(1, "flib", Some(42)).await.unwrap());
+        assert_eq!(subs.get_mods(1).await.unwrap(), vec![("flib".to_owned(), Some(42))]);
+        assert_eq!(subs.cached().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_updates_database_and_cache() {
+        let subs = test_service().await;
+        subs.subscribe_mod(1, "flib", None).await.unwrap();
+        subs.subscribe_mod(2, "flib", None).await.unwrap();
+        subs.subscribe_author(1, "flib", None).await.unwrap();
+        assert!(subs.unsubscribe_mod(1, "flib").await.unwrap());
+        assert!(!subs.unsubscribe_mod(1, "flib").await.unwrap());
+        assert!(subs.get_mods(1).await.unwrap().is_empty());
+        assert_eq!(subs.cached().unwrap(), vec![
+            entry(2, SubscriptionType::Modname("flib".to_owned())),
+            entry(1, SubscriptionType::Author("flib".to_owned())),
+        ]);
+        assert!(subs.unsubscribe_author(1, "flib").await.unwrap());
+        assert_eq!(subs.cached().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn clear_server_only_removes_that_server() {
+        let subs = test_service().await;
+        subs.subscribe_mod(1, "flib", None).await.unwrap();
+        subs.subscribe_author(1, "raiguard", None).await.unwrap();
+        subs.subscribe_mod(2, "flib", None).await.unwrap();
+        subs.clear_server(1).await.unwrap();
+        assert!(subs.get_mods(1).await.unwrap().is_empty());
+        assert!(subs.get_authors(1).await.unwrap().is_empty());
+        assert_eq!(subs.get_mods(2).await.unwrap().len(), 1);
+        assert_eq!(subs.cached().unwrap(), vec![entry(2, SubscriptionType::Modname("flib".to_owned()))]);
+
+        subs.reload().await.unwrap();
+        assert_eq!(subs.cached().unwrap(), vec![entry(2, SubscriptionType::Modname("flib".to_owned()))]);
+    }
+}
@@ -1,11 +1,11 @@
-use std::iter::once;
+use std::{fmt::Write, iter::once};
 use poise::serenity_prelude as serenity;
 use poise::reply::CreateReply;
 use sqlx::{Pool, Sqlite};
-use crate::{Context, Error, custom_errors::CustomError, Data, wiki_commands, mod_commands};
+use crate::{Context, Error, custom_errors::CustomError, Data, faq_commands, faq_suggest, wiki_commands, mod_commands, server_commands, subscriptions::Subscriptions, PREFIX};
 use regex::Regex;
 use serde::Deserialize;
-use log::info;
+use log::{error, info};
 
 #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
 pub async fn is_mod(ctx: Context<'_>) -> Result<bool, Error> {
@@ -56,25 +56,6 @@ pub async fn escape_formatting(unformatted_string: &str) -> String {
         .collect::<String>()
 }
 
-pub async fn get_subscribed_mods(db: &Pool<Sqlite>, server_id: i64) -> Result<Vec<String>, Error> {
-    let subscribed_mods = sqlx::query!(r#"SELECT mod_name FROM subscribed_mods WHERE server_id = $1"#, server_id)
-        .fetch_all(db)
-        .await?
-        .into_iter()
-        .map(|m| m.mod_name)
-        .collect::<Vec<String>>();
-    Ok(subscribed_mods)
-}
-pub async fn get_subscribed_authors(db: &Pool<Sqlite>, server_id: i64) -> Result<Vec<String>, Error> {
-    let subscribed_authors = sqlx::query!(r#"SELECT author_name FROM subscribed_authors WHERE server_id = $1"#, server_id)
-        .fetch_all(db)
-        .await?
-        .into_iter()
-        .filter_map(|m| m.author_name)
-        .collect::<Vec<String>>();
-    Ok(subscribed_authors)
-}
-
 /// Show stored information about this server
 #[poise::command(prefix_command, slash_command, guild_only, ephemeral, category="Settings")]
 pub async fn get_server_info(
@@ -91,8 +72,9 @@ pub async fn get_server_info(
             let updates_channel = data.updates_channel.map_or_else(|| "Not set".to_owned(), |ch| format!("<#{ch}>"));
             let modrole = data.modrole.map_or_else(|| "Not set".to_owned(), |role| format!("<@&{role}>"));
             let show_changelog = data.show_changelog.map_or_else(|| "Not set (default to true)".to_owned(), |b| b.to_string());
-            let response = format!("**Stored information for this server:**\nServer ID: {:?}\nUpdates channel: {}\nmodrole: {}\nShow changelogs: {}",
-                data.server_id, updates_channel, modrole, show_changelog);
+            let webhook_delivery = data.webhook_url.is_some();
+            let response = format!("**Stored information for this server:**\nServer ID: {:?}\nUpdates channel: {}\nmodrole: {}\nShow changelogs: {}\nWebhook delivery: {}",
+                data.server_id, updates_channel, modrole, show_changelog, webhook_delivery);
             ctx.say(response).await?;
         },
         None => {
@@ -119,17 +101,13 @@ pub async fn help(
     Ok(())
 }
 
-/// Remove all stored data for this server, resetting all settings.
+/// Deprecated, use /server reset instead.
+// Kept for a release so existing users are pointed to the replacement
 #[poise::command(prefix_command, slash_command, guild_only, category="Settings", check="is_mod")]
 pub async fn reset_server_settings(
     ctx: Context<'_>
 ) -> Result<(), Error> {
-    let server_id = get_server_id(ctx)?;
-    let db = &ctx.data().database;
-    sqlx::query!(r#"DELETE FROM servers WHERE server_id = $1"#, server_id)
-        .execute(db)
-        .await?;
-    ctx.say("Server data reset").await?;
+    ctx.say("This command was replaced by `/server reset`, which can also delete subscriptions, FAQ entries or everything stored for this server.").await?;
     Ok(())
 }
 
@@ -192,19 +170,19 @@ pub async fn import_legacy_faqs(
             link: if faq.link.is_empty() {None} else {Some(capitalize(&faq.link.to_lowercase()),)},
         };
 
-        sqlx::query!(r#"
-            INSERT INTO faq (server_id, title, contents, image, edit_time, author, link) 
-            VALUES ($1, $2, $3, $4, $5, $6, $7)"#, 
-            new_faq.server_id,
-            new_faq.title,
-            new_faq.content,
-            new_faq.image,
-            new_faq.timestamp,
-            new_faq.creator,
-            new_faq.link
-        )
-            .execute(db)
-            .await?;
+        if let Some(link) = new_faq.link {
+            sqlx::query!(r#"INSERT OR IGNORE INTO faq_aliases (server_id, alias, title) VALUES ($1, $2, $3)"#,
+                new_faq.server_id,
+                new_faq.title,
+                link
+            )
+                .execute(db)
+                .await?;
+            continue;
+        }
+        // Titles can repeat in legacy files, later entries replace earlier ones
+        let content = faq_commands::FaqContent { contents: new_faq.content, image: new_faq.image, body: None };
+        faq_commands::save_faq(db, new_faq.server_id, &new_faq.title, &content, new_faq.timestamp, new_faq.creator).await?;
     };
     ctx.say("Successfully imported all FAQ entries").await?;
     Ok(())
@@ -217,28 +195,21 @@ pub async fn drop_faqs(
 ) -> Result<(), Error> {
     let db = &ctx.data().database;
     let server_id = get_server_id(ctx)?;
-    sqlx::query!(r#"DELETE FROM faq WHERE server_id = $1"#, server_id)
-        .execute(db)
-        .await?;
+    server_commands::delete_server_data(db, &ctx.data().subscriptions, server_id, server_commands::ResetScope::Faqs).await?;
+    if let Err(e) = faq_commands::update_faq_cache(ctx.data().faq_cache.clone(), db.clone()).await {
+        error!("Error while updating faq cache: {e}");
+    }
+    if let Err(e) = ctx.data().faq_suggestions.reload().await {
+        error!("Error while updating faq suggestion cache: {e}");
+    }
     ctx.say("All FAQ entries for this server deleted").await?;
     Ok(())
 }
 
 #[allow(clippy::cast_possible_wrap)]
-pub async fn on_guild_leave(id: serenity::GuildId, db: Pool<Sqlite>) -> Result<(), Error> {
+pub async fn on_guild_leave(id: serenity::GuildId, db: Pool<Sqlite>, subscriptions: &Subscriptions) -> Result<(), Error> {
     let server_id = id.get() as i64;
-    sqlx::query!(r#"DELETE FROM servers WHERE server_id = $1"#, server_id)
-        .execute(&db)
-        .await?;
-    sqlx::query!(r#"DELETE FROM subscribed_mods WHERE server_id = $1"#, server_id)
-        .execute(&db)
-        .await?;
-    sqlx::query!(r#"DELETE FROM subscribed_authors WHERE server_id = $1"#, server_id)
-        .execute(&db)
-        .await?;
-    sqlx::query!(r#"DELETE FROM faq WHERE server_id = $1"#, server_id)
-        .execute(&db)
-        .await?;
+    server_commands::delete_server_data(&db, subscriptions, server_id, server_commands::ResetScope::Everything).await?;
     info!("Left guild {server_id}");
     Ok(())
 }
@@ -256,6 +227,12 @@ pub async fn send_custom_error_message(ctx: Context<'_>, msg: &str) -> Result<()
 #[allow(clippy::unnecessary_unwrap)]
 pub async fn on_message(ctx: serenity::Context, msg: &serenity::Message, data: &Data) -> Result<(), Error> {
     if msg.author.bot {return Ok(())};
+    if !msg.content.starts_with(PREFIX) {
+        // A failed suggestion should not keep wiki and mod links from being answered
+        if let Err(e) = faq_suggest::on_message(&ctx, msg, data).await {
+            error!("Error while suggesting faq entry: {e}");
+        }
+    }
     let wiki_regex = Regex::new(r"\[\[(.*?)\]\]").unwrap();
     let neg_wiki_regex = Regex::new(r"\`[\S\s]*?\[\[(.*?)\]\][\S\s]*?\`").unwrap();
     let wiki_captures = wiki_regex.captures(&msg.content);
@@ -296,6 +273,87 @@ pub async fn on_message(ctx: serenity::Context, msg: &serenity::Message, data: &
     Ok(())
 }
 
+/// Send embeds as pages of a single message, with buttons to move between them.
+// Not `poise::builtins::paginate`, which only pages plain text descriptions, always adds buttons
+// even for a single page, and has no room for the section menu of `paginate_sections`.
+pub async fn paginate_embeds(ctx: Context<'_>, pages: Vec<serenity::CreateEmbed>) -> Result<(), Error> {
+    paginate_sections(ctx, pages, Vec::new()).await
+}
+
+/// Like [`paginate_embeds`], with a select menu to jump to the first page of each named section.
+/// Only the first 25 sections fit in the menu.
+pub async fn paginate_sections(ctx: Context<'_>, pages: Vec<serenity::CreateEmbed>, sections: Vec<(String, usize)>) -> Result<(), Error> {
+    let page_count = pages.len();
+    let ctx_id = ctx.id();
+    let prev_button_id = format!("{ctx_id}prev");
+    let next_button_id = format!("{ctx_id}next");
+    let jump_menu_id = format!("{ctx_id}jump");
+    let make_page = |index: usize| {
+        let footer = serenity::CreateEmbedFooter::new(format!("Page {}/{page_count}", index + 1));
+        pages.get(index).cloned().unwrap_or_default().footer(footer)
+    };
+
+    let mut components = Vec::new();
+    if page_count > 1 {
+        components.push(serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new(&prev_button_id).emoji('◀'),
+            serenity::CreateButton::new(&next_button_id).emoji('▶'),
+        ]));
+    }
+    if sections.len() > 1 {
+        let options = sections.iter()
+            .take(25)
+            .map(|(name, page)| serenity::CreateSelectMenuOption::new(name, page.to_string()))
+            .collect();
+        components.push(serenity::CreateActionRow::SelectMenu(
+            serenity::CreateSelectMenu::new(&jump_menu_id, serenity::CreateSelectMenuKind::String { options })
+                .placeholder("Jump to...")
+        ));
+    }
+    let has_components = !components.is_empty();
+    ctx.send(CreateReply::default().embed(make_page(0)).components(components)).await?;
+    if !has_components {
+        return Ok(())
+    }
+
+    let mut current_page = 0;
+    while let Some(press) = serenity::ComponentInteractionCollector::new(ctx)
+        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
+        .timeout(std::time::Duration::from_mins(10))
+        .await
+    {
+        if press.data.custom_id == next_button_id {
+            current_page = (current_page + 1) % page_count;
+        } else if press.data.custom_id == prev_button_id {
+            current_page = (current_page + page_count - 1) % page_count;
+        } else if let serenity::ComponentInteractionDataKind::StringSelect { values } = &press.data.kind {
+            let Some(page) = values.first().and_then(|v| v.parse::<usize>().ok()).filter(|p| *p < page_count) else {
+                continue;
+            };
+            current_page = page;
+        } else {
+            continue;
+        }
+        let message = serenity::CreateInteractionResponseMessage::new().embed(make_page(current_page));
+        press.create_response(ctx, serenity::CreateInteractionResponse::UpdateMessage(message)).await?;
+    }
+    Ok(())
+}
+
+/// Join lines into an embed field value, cutting off before the field length limit.
+pub fn embed_list_field(items: &[String]) -> String {
+    let mut out = String::new();
+    for (i, item) in items.iter().enumerate() {
+        if out.len() + item.len() > 950 {
+            let _ = write!(out, "...and {} more", items.len() - i);
+            break;
+        }
+        out.push_str(item);
+        out.push('\n');
+    }
+    out
+}
+
 /// Capitalizes the first character in s.
 pub fn capitalize(s: &str) -> String {
     let mut c = s.chars();
@@ -343,4 +401,17 @@ pub fn api_resolve_internal_links(s: &str) -> String {
         output = output.replace(&capture.full, &format!("[{linktext}](https://lua-api.factorio.com/latest/{section}/{name}.html#{property})"));
     };
     output
-}
\ No newline at end of file
+}
+
+/// Empty in-memory database with all migrations applied, for tests
+#[cfg(test)]
+pub async fn test_database() -> Pool<Sqlite> {
+    // A single connection, as every connection to :memory: opens a separate database
+    let db = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations").run(&db).await.unwrap();
+    db
+}